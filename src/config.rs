@@ -1,4 +1,111 @@
+use crate::monitor::{brightness::Curve, power::Thresholds};
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, env, fs, io, path::PathBuf};
 
-#[derive(Deserialize, Serialize)]
-struct Config {}
+/// `$XDG_CONFIG_HOME/snacks/config.toml`, e.g.
+///
+/// ```toml
+/// [monitors]
+/// bluetooth = false
+///
+/// [volume]
+/// level_meter = true
+/// pin_muted_microphone = true
+/// feedback = true
+/// sound_theme = "ocean"
+///
+/// [brightness]
+/// curve = "linear"
+///
+/// [power]
+/// low = 15.0
+/// ```
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(default)]
+pub struct Config {
+    /// Whether a monitor runs, by [`crate::monitor::Monitor::name`], the missing ones run.
+    pub monitors: HashMap<String, bool>,
+    pub volume: Volume,
+    pub brightness: Brightness,
+    /// When the battery is low or critical.
+    pub power: Thresholds,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct Volume {
+    /// Show how loud the default sink is in the volume layer, a capture stream runs meanwhile.
+    pub level_meter: bool,
+    /// Keep the microphone layer open after the microphone is muted, until it is unmuted.
+    pub pin_muted_microphone: bool,
+    /// Play [`Volume::feedback_sound`] when the volume of the default sink changes.
+    pub feedback: bool,
+    /// The name of the sample in the sound theme.
+    pub feedback_sound: String,
+    /// The sample is looked up in this theme, then in the themes it inherits.
+    pub sound_theme: String,
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Self {
+            level_meter: false,
+            pin_muted_microphone: false,
+            feedback: false,
+            feedback_sound: "audio-volume-change".to_owned(),
+            sound_theme: "freedesktop".to_owned(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct Brightness {
+    /// A scroll step on the brightness layer, in the scale of [`Brightness::curve`].
+    pub step: f32,
+    pub curve: Curve,
+}
+
+impl Default for Brightness {
+    fn default() -> Self {
+        Self {
+            step: 0.05,
+            curve: Curve::Logarithmic,
+        }
+    }
+}
+
+impl Config {
+    /// The default config if the file is missing or invalid.
+    pub fn load() -> Self {
+        let Some(path) = path() else {
+            return Self::default();
+        };
+        let text = match fs::read_to_string(&path) {
+            Ok(x) => x,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                tracing::warn!("cannot read {}: {e}", path.display());
+                return Self::default();
+            }
+        };
+        match toml::from_str(&text) {
+            Ok(x) => x,
+            Err(e) => {
+                tracing::warn!("invalid config {}: {e}", path.display());
+                Self::default()
+            }
+        }
+    }
+    pub fn is_enabled(&self, monitor: &str) -> bool {
+        self.monitors.get(monitor).copied().unwrap_or(true)
+    }
+}
+
+fn path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|x| PathBuf::from(x).join(".config")))?;
+    Some(config_home.join("snacks").join("config.toml"))
+}
@@ -3,7 +3,7 @@ use cosmic::{
     iced::{
         self, Subscription,
         alignment::Vertical,
-        futures::channel::mpsc::Sender,
+        mouse::ScrollDelta,
         platform_specific::shell::commands::layer_surface::{self, Anchor, Layer},
         runtime::platform_specific::wayland::layer_surface::{
             IcedMargin, SctkLayerSurfaceSettings,
@@ -13,13 +13,33 @@ use cosmic::{
     prelude::Element,
     widget,
 };
-use monitor::mpris;
-use std::{error::Error, time::Duration};
-use tokio::task::JoinHandle;
+use monitor::{
+    bluetooth, brightness, keyboard_backlight, lock_keys, mpris, network, pipewire, power,
+    power_profiles, rfkill, udisks,
+};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 use update::Update;
 
 mod config;
 mod monitor;
+mod sound_theme;
+
+/// The end of the volume bar, PipeWire allows more but 150% is what most desktops let you set.
+const MAX_VOLUME: f32 = 1.5;
+const VOLUME_STEP: f32 = 0.05;
+const WINDOW_WIDTH: u32 = 600;
+const WINDOW_HEIGHT: u32 = 100;
+/// The extra height for every stream when the mixer is expanded.
+const MIXER_ROW_HEIGHT: u32 = 48;
+/// The extra height of the channel volumes, shown when the channels are unbalanced.
+const CHANNELS_ROW_HEIGHT: u32 = 32;
+const BALANCE_STEP: f32 = 0.05;
+/// Volume changes smaller than this are rounding errors of PipeWire, not changes.
+const FEEDBACK_TOLERANCE: f32 = 0.001;
+/// Keyboard backlights with more levels than this get a bar instead of one block per level.
+const MAX_KEYBOARD_BACKLIGHT_STEPS: u32 = 10;
+/// Touchpads scroll in pixels, this many pixels count as one mouse wheel step.
+const PIXELS_PER_SCROLL_LINE: f32 = 20.0;
 
 fn main() -> cosmic::iced::Result {
     tracing_subscriber::fmt()
@@ -32,26 +52,83 @@ fn main() -> cosmic::iced::Result {
         .client_decorations(false);
 
     tracing::info!("app running");
-    cosmic::app::run::<AppModel>(settings, ())?;
+    cosmic::app::run::<AppModel>(settings, config::Config::load())?;
     tracing::info!("app end");
 
     //stdout_loop().unwrap();
     Ok(())
 }
 
-// INFO:
-// 1. volume (pipewire)
-// 2. media (mpris)
-// 3. screen brightness (udev?)
-async fn event_loop(
-    sender: Sender<Message>,
-) -> Box<[Result<JoinHandle<()>, Box<dyn Error + Send + Sync>>]> {
-    Box::new([
-        // TODO: this will open a window on start, but we don't want that
-        monitor::pipewire::start(sender.clone(), |event| Message::Error(event))
-            .await
-            .map_err(|e| e.into()),
-        monitor::mpris::start(sender, |event| {
+/// Every source of snacks, [`config::Config::monitors`] turns them off by name.
+fn monitors(config: &config::Config) -> monitor::Registry<Message> {
+    monitor::Registry::default()
+        .register(pipewire::PipeWireMonitor::default(), |event| match event {
+            pipewire::Event::Error(error) => Message::Error(error),
+            event => Message::UpdateAudio(event),
+        })
+        .register(
+            brightness::BrightnessMonitor {
+                root: monitor::SYSFS_ROOT.into(),
+            },
+            |event| match event {
+                brightness::Event::Error(error) => Message::Error(error),
+                event => Message::UpdateBrightness(event),
+            },
+        )
+        .register(
+            keyboard_backlight::KeyboardBacklightMonitor {
+                root: monitor::SYSFS_ROOT.into(),
+            },
+            |event| match event {
+                keyboard_backlight::Event::Changed(backlight) => {
+                    Message::UpdateKeyboardBacklight(backlight)
+                }
+                keyboard_backlight::Event::Error(error) => Message::Error(error),
+            },
+        )
+        .register(
+            lock_keys::LockKeysMonitor {
+                root: monitor::SYSFS_ROOT.into(),
+            },
+            |event| match event {
+                lock_keys::Event::Changed { key, on } => Message::UpdateLockKey { key, on },
+            },
+        )
+        .register(
+            power::PowerMonitor {
+                thresholds: config.power,
+            },
+            |event| match event {
+                power::Event::Error(error) => Message::Error(error),
+                event => Message::UpdatePower(event),
+            },
+        )
+        .register(power_profiles::PowerProfilesMonitor, |event| match event {
+            power_profiles::Event::Error(error) => Message::Error(error),
+            event => Message::UpdatePowerProfile(event),
+        })
+        .register(
+            rfkill::RfkillMonitor {
+                root: monitor::SYSFS_ROOT.into(),
+            },
+            |event| match event {
+                rfkill::Event::Error(error) => Message::Error(error),
+                event => Message::UpdateRadio(event),
+            },
+        )
+        .register(bluetooth::BluetoothMonitor, |event| match event {
+            bluetooth::Event::Error(error) => Message::Error(error),
+            event => Message::UpdateBluetooth(event),
+        })
+        .register(network::NetworkMonitor, |event| match event {
+            network::Event::Error(error) => Message::Error(error),
+            event => Message::UpdateNetwork(event),
+        })
+        .register(udisks::UDisksMonitor, |event| match event {
+            udisks::Event::Error(error) => Message::Error(error),
+            event => Message::UpdateMount(event),
+        })
+        .register(mpris::MprisMonitor, |event| {
             use mpris::Event;
             match event {
                 Event::NewMethodCall => Message::OpenOrRefreshWindow,
@@ -62,17 +139,54 @@ async fn event_loop(
                 Event::Error(error) => Message::Error(error),
             }
         })
-        .await
-        .map_err(|e| e.into()),
-    ])
 }
 
 struct AppModel {
     core: Core,
     window: Option<Window>,
     timeout: Duration,
+    /// Loaded once on start, the monitors of [`AppModel::subscription`] are configured with it.
+    config: Arc<config::Config>,
+    /// Keep the microphone layer open after the microphone is muted.
+    pin_muted_microphone: bool,
+    /// The microphone was muted since the layer opened, the timeout doesn't close it.
+    microphone_pinned: bool,
+    /// Show how loud the default sink is in the volume layer, a capture stream runs meanwhile.
+    level_meter: bool,
     showing_layer: ShowingLayer,
     media_status: mpris::Properties,
+    volume_status: Option<pipewire::Volume>,
+    microphone_status: Option<pipewire::Volume>,
+    switched_node: Option<pipewire::SwitchedNode>,
+    /// Playback streams of applications, keyed by node id.
+    streams: BTreeMap<u32, pipewire::Stream>,
+    /// Show the streams in the volume layer.
+    mixer_expanded: bool,
+    /// The last level of the default sink, only while the volume layer is open.
+    level: Option<pipewire::Level>,
+    /// Played when the volume of the default sink changes.
+    feedback_sound: Option<Arc<pipewire::Sample>>,
+    audio_controller: Option<pipewire::Controller>,
+    /// The backlight that changed last, or the first one found.
+    brightness_status: Option<brightness::Backlight>,
+    brightness_controller: Option<brightness::Controller>,
+    brightness_config: config::Brightness,
+    keyboard_backlight_status: Option<keyboard_backlight::KeyboardBacklight>,
+    /// The lock key that changed last and whether it is on.
+    lock_key_status: Option<(lock_keys::LockKey, bool)>,
+    /// The last power event, never [`power::Event::Error`].
+    power_status: Option<power::Event>,
+    /// The `ActiveProfile` of power-profiles-daemon.
+    power_profile: Option<String>,
+    power_profiles_controller: Option<power_profiles::Controller>,
+    radio_status: Option<rfkill::Event>,
+    /// The device that connected or disconnected last.
+    bluetooth_status: Option<bluetooth::Device>,
+    /// The last network event, never [`network::Event::Error`].
+    network_status: Option<network::Event>,
+    /// The last mount or unmount and whether it was mounted.
+    mount_status: Option<(udisks::Mount, bool)>,
+    udisks_controller: Option<udisks::Controller>,
     error_message: Option<String>,
 }
 
@@ -83,7 +197,7 @@ struct Window {
 
 impl cosmic::Application for AppModel {
     type Executor = cosmic::executor::Default;
-    type Flags = ();
+    type Flags = config::Config;
     type Message = Message;
 
     const APP_ID: &'static str = "snacks";
@@ -94,15 +208,46 @@ impl cosmic::Application for AppModel {
     fn core_mut(&mut self) -> &mut Core {
         &mut self.core
     }
-    fn init(core: Core, _flags: Self::Flags) -> (Self, Task<Self::Message>) {
+    fn init(core: Core, config: Self::Flags) -> (Self, Task<Self::Message>) {
         assert!(core.main_window_id().is_none());
+        let config = Arc::new(config);
         (
             Self {
                 core,
                 window: None,
                 timeout: Duration::from_secs(2),
+                config: config.clone(),
+                pin_muted_microphone: config.volume.pin_muted_microphone,
+                microphone_pinned: false,
+                level_meter: config.volume.level_meter,
                 showing_layer: ShowingLayer::default(),
                 media_status: mpris::Properties::default(),
+                volume_status: None,
+                microphone_status: None,
+                switched_node: None,
+                streams: BTreeMap::new(),
+                mixer_expanded: false,
+                level: None,
+                feedback_sound: config.volume.feedback.then(|| {
+                    Arc::new(sound_theme::load(
+                        &config.volume.sound_theme,
+                        &config.volume.feedback_sound,
+                    ))
+                }),
+                audio_controller: None,
+                brightness_status: None,
+                brightness_controller: None,
+                brightness_config: config.brightness,
+                keyboard_backlight_status: None,
+                lock_key_status: None,
+                power_status: None,
+                power_profile: None,
+                power_profiles_controller: None,
+                radio_status: None,
+                bluetooth_status: None,
+                network_status: None,
+                mount_status: None,
+                udisks_controller: None,
                 error_message: None,
             },
             Task::none(),
@@ -115,7 +260,10 @@ impl cosmic::Application for AppModel {
         }
     }
     fn update(&mut self, message: Self::Message) -> Task<Self::Message> {
-        tracing::info!("update: {:#?}", message);
+        // the meter sends a level 20 times a second
+        if !matches!(message, Message::UpdateAudio(pipewire::Event::Level(_))) {
+            tracing::info!("update: {:#?}", message);
+        }
         match message {
             Message::UpdateMedia(update) => {
                 self.showing_layer = ShowingLayer::Media;
@@ -128,6 +276,287 @@ impl cosmic::Application for AppModel {
                 }
                 Task::none()
             }
+            Message::UpdateAudio(event) => match event {
+                pipewire::Event::Volume(volume) => {
+                    let (status, layer) = match volume.kind {
+                        pipewire::DefaultNode::Sink => {
+                            (&mut self.volume_status, ShowingLayer::Volume)
+                        }
+                        pipewire::DefaultNode::Source => {
+                            (&mut self.microphone_status, ShowingLayer::Microphone)
+                        }
+                    };
+                    // don't show the first volume we got on start or after switching to another
+                    // node, only the changes
+                    let node_id = volume.node_id;
+                    let level = volume.volume;
+                    let mute = volume.mute;
+                    let previous = status.replace(volume).filter(|x| x.node_id == node_id);
+                    // our own changes are already in the status, only the others make a sound
+                    let level_changed = previous
+                        .as_ref()
+                        .is_some_and(|x| (x.volume - level).abs() > FEEDBACK_TOLERANCE);
+                    if level_changed && matches!(layer, ShowingLayer::Volume) {
+                        self.play_feedback();
+                    }
+                    // only a mute change pins or unpins, a volume change while muted doesn't
+                    let mute_changed = previous.as_ref().is_some_and(|x| x.mute != mute);
+                    if mute_changed && matches!(layer, ShowingLayer::Microphone) {
+                        self.microphone_pinned = self.pin_muted_microphone && mute;
+                    }
+                    if previous.is_some() {
+                        self.showing_layer = layer;
+                        Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+                    } else {
+                        Task::none()
+                    }
+                }
+                pipewire::Event::DefaultNodeChanged { kind, name: None } => {
+                    match kind {
+                        pipewire::DefaultNode::Sink => self.volume_status = None,
+                        pipewire::DefaultNode::Source => self.microphone_status = None,
+                    }
+                    Task::none()
+                }
+                pipewire::Event::StreamVolume(stream) => {
+                    let node_id = stream.node_id;
+                    // the first volume of a stream is the one it started with, don't show it
+                    if self.streams.insert(node_id, stream).is_some() {
+                        self.showing_layer = ShowingLayer::Stream(node_id);
+                        Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+                    } else {
+                        Task::none()
+                    }
+                }
+                pipewire::Event::NodeRemoved(id) => {
+                    self.streams.remove(&id);
+                    Task::none()
+                }
+                pipewire::Event::DefaultNodeSwitched(switched) => {
+                    self.switched_node = Some(switched);
+                    self.showing_layer = ShowingLayer::SwitchedNode;
+                    Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+                }
+                pipewire::Event::Level(level) => {
+                    self.level = Some(level);
+                    Task::none()
+                }
+                pipewire::Event::Ready(controller) => {
+                    // after a reconnection everything is sent again, what we have is stale
+                    self.volume_status = None;
+                    self.microphone_status = None;
+                    self.streams.clear();
+                    self.audio_controller = Some(controller);
+                    Task::none()
+                }
+                _ => Task::none(),
+            },
+            Message::UpdateBrightness(event) => match event {
+                brightness::Event::Ready(controller) => {
+                    self.brightness_controller = Some(controller);
+                    Task::none()
+                }
+                brightness::Event::Added(backlight) => {
+                    if self.brightness_status.is_none() {
+                        self.brightness_status = Some(backlight);
+                    }
+                    Task::none()
+                }
+                brightness::Event::Changed(backlight) => {
+                    self.brightness_status = Some(backlight);
+                    self.showing_layer = ShowingLayer::Brightness;
+                    Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+                }
+                // `monitors` sends it as `Message::Error`
+                brightness::Event::Error(_) => Task::none(),
+            },
+            Message::UpdateKeyboardBacklight(backlight) => {
+                self.keyboard_backlight_status = Some(backlight);
+                self.showing_layer = ShowingLayer::KeyboardBacklight;
+                Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+            }
+            Message::UpdateLockKey { key, on } => {
+                self.lock_key_status = Some((key, on));
+                self.showing_layer = ShowingLayer::LockKey;
+                Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+            }
+            Message::UpdatePower(event) => {
+                self.power_status = Some(event);
+                self.showing_layer = ShowingLayer::Power;
+                Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+            }
+            Message::UpdateRadio(event) => {
+                self.radio_status = Some(event);
+                self.showing_layer = ShowingLayer::Radio;
+                Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+            }
+            Message::UpdateBluetooth(event) => match event {
+                bluetooth::Event::Connected(device) | bluetooth::Event::Disconnected(device) => {
+                    self.bluetooth_status = Some(device);
+                    self.showing_layer = ShowingLayer::Bluetooth;
+                    Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+                }
+                // the battery is often reported right after connecting, only the open layer follows
+                bluetooth::Event::BatteryChanged(device) => {
+                    let showing = self.window.is_some()
+                        && matches!(self.showing_layer, ShowingLayer::Bluetooth)
+                        && self
+                            .bluetooth_status
+                            .as_ref()
+                            .is_some_and(|x| x.path == device.path);
+                    if showing {
+                        self.bluetooth_status = Some(device);
+                    }
+                    Task::none()
+                }
+                // `monitors` sends it as `Message::Error`
+                bluetooth::Event::Error(_) => Task::none(),
+            },
+            Message::UpdateNetwork(event) => {
+                self.network_status = Some(event);
+                self.showing_layer = ShowingLayer::Network;
+                Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+            }
+            Message::UpdateMount(event) => match event {
+                udisks::Event::Ready(controller) => {
+                    self.udisks_controller = Some(controller);
+                    Task::none()
+                }
+                udisks::Event::Mounted(mount) => {
+                    self.mount_status = Some((mount, true));
+                    self.showing_layer = ShowingLayer::Mount;
+                    Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+                }
+                udisks::Event::Unmounted(mount) => {
+                    self.mount_status = Some((mount, false));
+                    self.showing_layer = ShowingLayer::Mount;
+                    Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+                }
+                // `monitors` sends it as `Message::Error`
+                udisks::Event::Error(_) => Task::none(),
+            },
+            Message::Eject => {
+                let (Some(controller), Some((mount, true))) =
+                    (self.udisks_controller.clone(), self.mount_status.clone())
+                else {
+                    return Task::none();
+                };
+                // the unmount comes back through the monitor
+                Task::future(async move {
+                    match controller.eject(&mount).await {
+                        Ok(()) => cosmic::Action::None,
+                        Err(e) => cosmic::Action::App(Message::Error(format!(
+                            "cannot eject {}: {e}",
+                            mount.name
+                        ))),
+                    }
+                })
+            }
+            Message::UpdatePowerProfile(event) => match event {
+                power_profiles::Event::Ready(controller) => {
+                    self.power_profiles_controller = Some(controller);
+                    Task::none()
+                }
+                power_profiles::Event::Changed(profile) => {
+                    self.power_profile = Some(profile);
+                    self.showing_layer = ShowingLayer::PowerProfile;
+                    Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+                }
+                // `monitors` sends it as `Message::Error`
+                power_profiles::Event::Error(_) => Task::none(),
+            },
+            Message::CyclePowerProfile => {
+                let Some(controller) = self.power_profiles_controller.clone() else {
+                    return Task::none();
+                };
+                // the new profile comes back through the monitor
+                let cycle = Task::future(async move {
+                    match controller.cycle().await {
+                        Ok(_) => cosmic::Action::None,
+                        Err(e) => cosmic::Action::App(Message::Error(format!(
+                            "cannot change the power profile: {e}"
+                        ))),
+                    }
+                });
+                cycle.chain(Task::done(cosmic::Action::App(
+                    Message::OpenOrRefreshWindow,
+                )))
+            }
+            Message::ControlBrightness(control) => {
+                let (Some(controller), Some(backlight)) =
+                    (&self.brightness_controller, &mut self.brightness_status)
+                else {
+                    return Task::none();
+                };
+                let brightness = control.apply(backlight, self.brightness_config);
+                let controller = controller.clone();
+                let name = backlight.name.clone();
+                let set_brightness = Task::future(async move {
+                    match controller.set(&name, brightness).await {
+                        Ok(()) => cosmic::Action::None,
+                        Err(e) => cosmic::Action::App(Message::Error(format!(
+                            "cannot set the brightness of {name}: {e}"
+                        ))),
+                    }
+                });
+                set_brightness.chain(Task::done(cosmic::Action::App(
+                    Message::OpenOrRefreshWindow,
+                )))
+            }
+            Message::ControlVolume(control) => {
+                let (Some(controller), Some(volume)) =
+                    (&self.audio_controller, &mut self.volume_status)
+                else {
+                    return Task::none();
+                };
+                let changes_level = !matches!(control, VolumeControl::ToggleMute);
+                controller.send(control.apply(
+                    volume.node_id,
+                    &mut volume.volume,
+                    &mut volume.mute,
+                ));
+                if changes_level {
+                    self.play_feedback();
+                }
+                Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+            }
+            Message::ControlBalance(balance) => {
+                let (Some(controller), Some(volume)) =
+                    (&self.audio_controller, &mut self.volume_status)
+                else {
+                    return Task::none();
+                };
+                let volumes = volume.channel_volumes_with_balance(balance);
+                for (channel, x) in volume.channels.iter_mut().zip(&volumes) {
+                    channel.volume = *x;
+                }
+                controller.send(pipewire::Command::SetChannelVolumes {
+                    node_id: volume.node_id,
+                    volumes,
+                });
+                Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+            }
+            Message::ControlStream { node_id, control } => {
+                let (Some(controller), Some(stream)) =
+                    (&self.audio_controller, self.streams.get_mut(&node_id))
+                else {
+                    return Task::none();
+                };
+                controller.send(control.apply(node_id, &mut stream.volume, &mut stream.mute));
+                Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
+            }
+            Message::ToggleMixer => {
+                self.mixer_expanded = !self.mixer_expanded;
+                let resize = match &self.window {
+                    Some(Window { id, .. }) => {
+                        layer_surface::set_size(*id, Some(WINDOW_WIDTH), Some(self.window_height()))
+                    }
+                    None => Task::none(),
+                };
+                resize.chain(Task::done(cosmic::Action::App(
+                    Message::OpenOrRefreshWindow,
+                )))
+            }
             Message::Error(e) => {
                 self.error_message = Some(format!("error: {e}"));
                 Task::done(cosmic::Action::App(Message::OpenOrRefreshWindow))
@@ -137,11 +566,20 @@ impl cosmic::Application for AppModel {
                 Task::done(cosmic::Action::App(Message::CloseWindow))
             }
             Message::OpenOrRefreshWindow => {
+                if let Some(controller) = &self.audio_controller {
+                    let metering =
+                        self.level_meter && matches!(self.showing_layer, ShowingLayer::Volume);
+                    controller.send(if metering {
+                        pipewire::Command::StartMeter
+                    } else {
+                        pipewire::Command::StopMeter
+                    });
+                }
                 let timeout = self.timeout.clone();
                 let (close_timer, handle) = Task::future(async move {
                     tokio::time::sleep(timeout).await;
                     tracing::info!("timeout!!!");
-                    cosmic::Action::App(Message::CloseWindow)
+                    cosmic::Action::App(Message::Timeout)
                 })
                 .abortable();
 
@@ -155,7 +593,13 @@ impl cosmic::Application for AppModel {
                             id,
                             close_timer_abort_handle: handle,
                         });
-                        close_timer
+                        // the content of the layer may need more or less room than before
+                        let resize = layer_surface::set_size(
+                            id,
+                            Some(WINDOW_WIDTH),
+                            Some(self.window_height()),
+                        );
+                        Task::batch([resize, close_timer])
                     }
                     None => {
                         let (window_id, open_window) = window::open(window::Settings::default());
@@ -164,7 +608,7 @@ impl cosmic::Application for AppModel {
                                 id: window_id,
                                 layer: Layer::Overlay,
                                 anchor: Anchor::BOTTOM,
-                                size: Some((Some(600), Some(100))), // TODO: avoid this
+                                size: Some((Some(WINDOW_WIDTH), Some(self.window_height()))), // TODO: avoid this
                                 margin: IcedMargin {
                                     bottom: 100,
                                     ..Default::default()
@@ -189,6 +633,17 @@ impl cosmic::Application for AppModel {
                     }
                 }
             }
+            Message::Timeout => {
+                let pinned = self.microphone_pinned
+                    && matches!(self.showing_layer, ShowingLayer::Microphone)
+                    && self.microphone_status.as_ref().is_some_and(|x| x.mute);
+                if pinned {
+                    // stays open without a timer, unmuting or another layer starts a new one
+                    Task::none()
+                } else {
+                    Task::done(cosmic::Action::App(Message::CloseWindow))
+                }
+            }
             Message::CloseWindow => match self.window.take() {
                 Some(Window {
                     id,
@@ -196,6 +651,12 @@ impl cosmic::Application for AppModel {
                 }) => {
                     tracing::info!("closing window {id}");
                     close_timer_abort_handle.abort();
+                    self.mixer_expanded = false;
+                    self.microphone_pinned = false;
+                    self.level = None;
+                    if let Some(controller) = &self.audio_controller {
+                        controller.send(pipewire::Command::StopMeter);
+                    }
                     window::close(id).chain(layer_surface::destroy_layer_surface(id))
                 }
                 None => {
@@ -206,28 +667,48 @@ impl cosmic::Application for AppModel {
         }
     }
     fn subscription(&self) -> Subscription<Self::Message> {
-        // FIXME: some how start all monitors
-        Subscription::run(|| {
+        let config = self.config.clone();
+        // the id keeps the same subscription running, the stream built on the next calls is dropped
+        Subscription::run_with_id(
+            "monitors",
             // TODO: is 100 the size of channel?
-            stream::channel(100, async |sender| {
-                let handles = event_loop(sender).await; // TODO: handle errors
-                for handle in handles {
-                    if let Err(e) = handle {
-                        tracing::error!(e)
-                    }
-                }
-            })
-        })
+            stream::channel(100, async move |sender| {
+                let mut monitors = monitors(&config);
+                monitors.start(sender, &config).await;
+                // the monitors stop when the subscription drops this future
+                std::future::pending::<()>().await;
+            }),
+        )
     }
     fn view_window(&self, _id: window::Id) -> Element<Self::Message> {
         match self.showing_layer {
             ShowingLayer::Media => self.media_status_view(),
             ShowingLayer::Volume => self.volume_status_view(),
+            ShowingLayer::Microphone => self.microphone_status_view(),
+            ShowingLayer::SwitchedNode => self.switched_node_view(),
+            ShowingLayer::Stream(node_id) => self.stream_view(node_id),
+            ShowingLayer::Brightness => self.brightness_status_view(),
+            ShowingLayer::KeyboardBacklight => self.keyboard_backlight_view(),
+            ShowingLayer::LockKey => self.lock_key_view(),
+            ShowingLayer::Power => self.power_view(),
+            ShowingLayer::PowerProfile => self.power_profile_view(),
+            ShowingLayer::Radio => self.radio_view(),
+            ShowingLayer::Bluetooth => self.bluetooth_view(),
+            ShowingLayer::Network => self.network_view(),
+            ShowingLayer::Mount => self.mount_view(),
             ShowingLayer::None => widget::row().into(),
         }
     }
 }
 
+impl Drop for AppModel {
+    fn drop(&mut self) {
+        if let Some(controller) = &self.audio_controller {
+            controller.shutdown();
+        }
+    }
+}
+
 impl AppModel {
     fn media_status_view(&self) -> Element<Message> {
         let metadata = self.media_status.metadata.as_ref();
@@ -256,7 +737,7 @@ impl AppModel {
             .size(42)
         });
 
-        widget::container(
+        snack(
             widget::row()
                 .align_y(Vertical::Center)
                 .spacing(8)
@@ -269,6 +750,576 @@ impl AppModel {
                 )
                 .push_maybe(playback),
         )
+    }
+    fn volume_status_view(&self) -> Element<Message> {
+        let volume = self.volume_status.as_ref();
+        let level = volume.map(|x| x.volume).unwrap_or_default();
+        let mute = volume.is_none_or(|x| x.mute);
+        let icon = iced::widget::mouse_area(
+            widget::text(if mute || level <= 0.0 {
+                ""
+            } else if level < 0.34 {
+                ""
+            } else if level < 0.67 {
+                ""
+            } else {
+                ""
+            })
+            .size(42),
+        )
+        .on_press(Message::ControlVolume(VolumeControl::ToggleMute));
+        let description = volume
+            .and_then(|x| x.description.as_ref())
+            .map(|x| widget::text(x).size(22));
+        let set_volume = |x| Message::ControlVolume(VolumeControl::Set(x));
+        // the part over 100% is a separate bar, so it is clear when the sink is boosted
+        let boost = (level > 1.0).then(|| {
+            widget::container(
+                iced::widget::slider(1.0..=MAX_VOLUME, level, set_volume).step(VOLUME_STEP),
+            )
+            .width(iced::Length::FillPortion(1))
+        });
+        let bar = widget::row()
+            .align_y(Vertical::Center)
+            .spacing(8)
+            .push(
+                widget::container(
+                    iced::widget::slider(0.0..=1.0, level.min(1.0), set_volume).step(VOLUME_STEP),
+                )
+                .width(iced::Length::FillPortion(2)),
+            )
+            .push_maybe(boost)
+            .push(widget::text(format!("{:.0}%", level * 100.0)));
+
+        let expand = (!self.streams.is_empty()).then(|| {
+            iced::widget::mouse_area(
+                widget::text(if self.mixer_expanded { "" } else { "" }).size(28),
+            )
+            .on_press(Message::ToggleMixer)
+        });
+        // the loudness, with the peaks as a thinner bar under it
+        let meter = self.level.filter(|_| self.level_meter).map(|x| {
+            widget::column()
+                .spacing(2)
+                .push(iced::widget::progress_bar(0.0..=1.0, x.rms.min(1.0)).height(4))
+                .push(iced::widget::progress_bar(0.0..=1.0, x.peak.min(1.0)).height(2))
+        });
+        let channels = volume.filter(|x| !x.is_balanced()).map(channels_row);
+        let mixer = self.mixer_expanded.then(|| {
+            widget::column::with_children(self.streams.values().map(stream_row).collect())
+                .spacing(8)
+        });
+
+        let layer = snack(
+            widget::column()
+                .spacing(8)
+                .push(
+                    widget::row()
+                        .align_y(Vertical::Center)
+                        .spacing(8)
+                        .push(icon)
+                        .push(
+                            widget::column()
+                                .width(iced::Length::Fill)
+                                .spacing(4)
+                                .push_maybe(description)
+                                .push(bar)
+                                .push_maybe(meter)
+                                .push_maybe(channels),
+                        )
+                        .push_maybe(expand),
+                )
+                .push_maybe(mixer),
+        );
+        iced::widget::mouse_area(layer)
+            .on_scroll(|delta| Message::ControlVolume(VolumeControl::Scroll(delta)))
+            .into()
+    }
+    fn microphone_status_view(&self) -> Element<Message> {
+        let microphone = self.microphone_status.as_ref();
+        let mute = microphone.is_none_or(|x| x.mute);
+        let icon = widget::text(if mute { "" } else { "" }).size(42);
+        let state = widget::text(if mute {
+            "Microphone muted"
+        } else {
+            "Microphone on"
+        })
+        .size(22);
+        let description = microphone
+            .and_then(|x| x.description.as_ref())
+            .map(|x| widget::text(x));
+        let level = microphone
+            .filter(|_| !mute)
+            .map(|x| widget::text(format!("{:.0}%", x.volume * 100.0)));
+
+        snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(icon)
+                .push(
+                    widget::column()
+                        .push(state)
+                        .push_maybe(description)
+                        .push_maybe(level),
+                ),
+        )
+    }
+    fn switched_node_view(&self) -> Element<Message> {
+        let Some(switched) = &self.switched_node else {
+            return widget::row().into();
+        };
+        let icon: Element<Message> = match &switched.icon_name {
+            Some(name) => widget::icon::from_name(name.as_str())
+                .size(42)
+                .icon()
+                .into(),
+            None => widget::text(match switched.form_factor.as_deref() {
+                Some("headset" | "headphone" | "hands-free") => "",
+                Some("microphone" | "webcam") => "",
+                Some("tv") => "",
+                _ => match switched.kind {
+                    pipewire::DefaultNode::Sink => "",
+                    pipewire::DefaultNode::Source => "",
+                },
+            })
+            .size(42)
+            .into(),
+        };
+        let title = widget::text(match switched.kind {
+            pipewire::DefaultNode::Sink => "Now playing on:",
+            pipewire::DefaultNode::Source => "Now recording from:",
+        });
+        let name = widget::text(switched.description.as_ref().unwrap_or(&switched.name)).size(22);
+
+        snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(icon)
+                .push(widget::column().push(title).push(name)),
+        )
+    }
+    fn stream_view(&self, node_id: u32) -> Element<Message> {
+        match self.streams.get(&node_id) {
+            Some(stream) => snack(stream_row(stream)),
+            None => widget::row().into(),
+        }
+    }
+    fn brightness_status_view(&self) -> Element<Message> {
+        let level = self
+            .brightness_status
+            .as_ref()
+            .map(|x| x.fraction())
+            .unwrap_or_default();
+        let position = self.brightness_config.curve.to_position(level);
+        let icon = widget::text(if level < 0.34 {
+            ""
+        } else if level < 0.67 {
+            ""
+        } else {
+            ""
+        })
+        .size(42);
+        let bar = widget::row()
+            .align_y(Vertical::Center)
+            .spacing(8)
+            .push(
+                iced::widget::slider(0.0..=1.0, position, |x| {
+                    Message::ControlBrightness(BrightnessControl::Set(x))
+                })
+                .step(0.01),
+            )
+            .push(widget::text(format!("{:.0}%", level * 100.0)));
+
+        let layer = snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(icon)
+                .push(
+                    widget::column()
+                        .width(iced::Length::Fill)
+                        .spacing(4)
+                        .push(widget::text("Brightness").size(22))
+                        .push(bar),
+                ),
+        );
+        iced::widget::mouse_area(layer)
+            .on_scroll(|delta| Message::ControlBrightness(BrightnessControl::Scroll(delta)))
+            .into()
+    }
+    fn keyboard_backlight_view(&self) -> Element<Message> {
+        let Some(backlight) = self.keyboard_backlight_status else {
+            return widget::row().into();
+        };
+        let icon = widget::text("").size(42);
+        let state = widget::text(if backlight.brightness == 0 {
+            "Keyboard backlight off"
+        } else {
+            "Keyboard backlight"
+        })
+        .size(22);
+        // one block per level, most keyboards have 2 or 3
+        let steps: Element<Message> = if backlight.max_brightness <= MAX_KEYBOARD_BACKLIGHT_STEPS {
+            widget::row::with_children(
+                (1..=backlight.max_brightness)
+                    .map(|step| {
+                        let on = step <= backlight.brightness;
+                        widget::container(iced::widget::Space::new(
+                            iced::Length::Fill,
+                            iced::Length::Fixed(8.0),
+                        ))
+                        .width(iced::Length::Fill)
+                        .style(move |_| {
+                            let color = if on {
+                                iced::Color::WHITE
+                            } else {
+                                iced::Color::from_rgb(0.3, 0.3, 0.3)
+                            };
+                            widget::container::background(color)
+                                .border(iced::Border::default().rounded(4))
+                        })
+                        .into()
+                    })
+                    .collect(),
+            )
+            .spacing(8)
+            .into()
+        } else {
+            let level = backlight.brightness as f32 / backlight.max_brightness as f32;
+            iced::widget::progress_bar(0.0..=1.0, level).into()
+        };
+
+        snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(icon)
+                .push(
+                    widget::column()
+                        .width(iced::Length::Fill)
+                        .spacing(4)
+                        .push(state)
+                        .push(steps),
+                ),
+        )
+    }
+    fn lock_key_view(&self) -> Element<Message> {
+        let Some((key, on)) = self.lock_key_status else {
+            return widget::row().into();
+        };
+        let icon = widget::text(match key {
+            lock_keys::LockKey::Caps => "",
+            lock_keys::LockKey::Num => "",
+            lock_keys::LockKey::Scroll => "",
+        })
+        .size(42);
+        let state =
+            widget::text(format!("{} {}", key.name(), if on { "on" } else { "off" })).size(22);
+
+        snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(icon)
+                .push(state),
+        )
+    }
+    fn power_view(&self) -> Element<Message> {
+        let (icon, title, status) = match &self.power_status {
+            Some(power::Event::PluggedIn(status)) => ("", "Charging", status),
+            Some(power::Event::Unplugged(status)) => ("", "On battery", status),
+            Some(power::Event::Low(status)) => ("", "Battery low", status),
+            Some(power::Event::Critical(status)) => ("", "Battery critical", status),
+            Some(power::Event::Error(_)) | None => return widget::row().into(),
+        };
+        let percentage = status.percentage.map(|x| format!("{x:.0}%"));
+        let detail = match (status.state, status.time_to_empty) {
+            (power::BatteryState::FullyCharged, _) => Some("fully charged".to_owned()),
+            (_, Some(time)) if status.on_battery => {
+                let minutes = time.as_secs() / 60;
+                Some(format!("{} h {:02} min left", minutes / 60, minutes % 60))
+            }
+            _ => None,
+        };
+        let subtitle = match (percentage, detail) {
+            (Some(percentage), Some(detail)) => Some(format!("{percentage}, {detail}")),
+            (percentage, detail) => percentage.or(detail),
+        };
+
+        snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(widget::text(icon).size(42))
+                .push(
+                    widget::column()
+                        .push(widget::text(title).size(22))
+                        .push_maybe(subtitle.map(widget::text)),
+                ),
+        )
+    }
+    fn power_profile_view(&self) -> Element<Message> {
+        let Some(profile) = &self.power_profile else {
+            return widget::row().into();
+        };
+        let icon = widget::text(match profile.as_str() {
+            "power-saver" => "",
+            "performance" => "",
+            _ => "",
+        })
+        .size(42);
+
+        let layer = snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(icon)
+                .push(
+                    widget::column()
+                        .push(widget::text("Power profile"))
+                        .push(widget::text(power_profiles::display_name(profile)).size(22)),
+                ),
+        );
+        iced::widget::mouse_area(layer)
+            .on_press(Message::CyclePowerProfile)
+            .into()
+    }
+    fn radio_view(&self) -> Element<Message> {
+        let (icon, state, detail) = match self.radio_status {
+            Some(rfkill::Event::AirplaneMode(true)) => ("", "Airplane mode on".to_owned(), None),
+            Some(rfkill::Event::AirplaneMode(false)) => ("", "Airplane mode off".to_owned(), None),
+            Some(rfkill::Event::Changed { radio, block }) => {
+                let icon = match (radio, block.is_blocked()) {
+                    (rfkill::Radio::Wlan, false) => "",
+                    (rfkill::Radio::Wlan, true) => "",
+                    (rfkill::Radio::Bluetooth, false) => "",
+                    (rfkill::Radio::Bluetooth, true) => "",
+                    (rfkill::Radio::Wwan, false) => "",
+                    (rfkill::Radio::Wwan, true) => "",
+                };
+                let state = if block.is_blocked() { "off" } else { "on" };
+                let detail = block.hard.then_some("Blocked by the hardware switch");
+                (icon, format!("{} {state}", radio.name()), detail)
+            }
+            Some(rfkill::Event::Error(_)) | None => return widget::row().into(),
+        };
+
+        snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(widget::text(icon).size(42))
+                .push(
+                    widget::column()
+                        .push(widget::text(state).size(22))
+                        .push_maybe(detail.map(widget::text)),
+                ),
+        )
+    }
+    fn bluetooth_view(&self) -> Element<Message> {
+        let Some(device) = &self.bluetooth_status else {
+            return widget::row().into();
+        };
+        let icon = match device.icon.as_deref() {
+            _ if !device.connected => "",
+            Some("audio-headset" | "audio-headphones") => "",
+            Some("audio-card") => "",
+            Some("input-mouse") => "",
+            Some("input-keyboard") => "",
+            Some("input-gaming") => "",
+            Some("phone") => "",
+            Some("computer") => "",
+            _ => "",
+        };
+        let state = if device.connected {
+            "Connected"
+        } else {
+            "Disconnected"
+        };
+        let state = match device.battery {
+            Some(battery) => format!("{state}, battery {battery}%"),
+            None => state.to_owned(),
+        };
+
+        snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(widget::text(icon).size(42))
+                .push(
+                    widget::column()
+                        .push(widget::text(&device.alias).size(22))
+                        .push(widget::text(state)),
+                ),
+        )
+    }
+    fn network_view(&self) -> Element<Message> {
+        let (connected, status) = match &self.network_status {
+            Some(network::Event::Connected(status)) => (true, status),
+            Some(network::Event::Disconnected(status)) => (false, status),
+            Some(network::Event::ConnectivityChanged(status)) => (
+                !matches!(status.connectivity, network::Connectivity::None),
+                status,
+            ),
+            Some(network::Event::Error(_)) | None => return widget::row().into(),
+        };
+        let icon = match status.connection.as_ref().map(|x| x.kind) {
+            Some(network::ConnectionKind::Wireless) if !connected => "",
+            _ if !connected => "",
+            Some(network::ConnectionKind::Wired) => "",
+            Some(network::ConnectionKind::Wireless) => "",
+            Some(network::ConnectionKind::Vpn) => "",
+            Some(network::ConnectionKind::Mobile) => "",
+            Some(network::ConnectionKind::Bluetooth) => "",
+            Some(network::ConnectionKind::Other) | None => "",
+        };
+        let name = status
+            .connection
+            .as_ref()
+            .map_or("No connection", |x| x.id.as_str());
+        let connectivity = match status.connectivity {
+            _ if !connected => "Disconnected",
+            // unknown when the connectivity check is off
+            network::Connectivity::Full | network::Connectivity::Unknown => "Connected",
+            network::Connectivity::Limited => "Limited connectivity",
+            network::Connectivity::Portal => "Sign in to the network",
+            network::Connectivity::None => "No internet",
+        };
+
+        snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(widget::text(icon).size(42))
+                .push(
+                    widget::column()
+                        .push(widget::text(name).size(22))
+                        .push(widget::text(connectivity)),
+                ),
+        )
+    }
+    fn mount_view(&self) -> Element<Message> {
+        let Some((mount, mounted)) = &self.mount_status else {
+            return widget::row().into();
+        };
+        let state = if *mounted {
+            format!("Mounted at {}", mount.mount_point.display())
+        } else {
+            "Safe to remove".to_owned()
+        };
+        let eject = mounted
+            .then(|| iced::widget::mouse_area(widget::text("").size(42)).on_press(Message::Eject));
+
+        snack(
+            widget::row()
+                .align_y(Vertical::Center)
+                .spacing(8)
+                .push(widget::text("").size(42))
+                .push(
+                    widget::column()
+                        .push(widget::text(&mount.name).size(22))
+                        .push(widget::text(state)),
+                )
+                .push(iced::widget::Space::with_width(iced::Length::Fill))
+                .push_maybe(eject),
+        )
+    }
+    fn play_feedback(&self) {
+        if let (Some(controller), Some(sound)) = (&self.audio_controller, &self.feedback_sound) {
+            controller.send(pipewire::Command::PlayFeedback(sound.clone()));
+        }
+    }
+    fn window_height(&self) -> u32 {
+        let mut height = WINDOW_HEIGHT;
+        // the mixer and the channels are only on the volume layer, the mixer stays expanded
+        // while another layer is shown
+        let volume = matches!(self.showing_layer, ShowingLayer::Volume);
+        if volume && self.mixer_expanded {
+            height += MIXER_ROW_HEIGHT * self.streams.len() as u32;
+        }
+        let unbalanced = volume
+            && self
+                .volume_status
+                .as_ref()
+                .is_some_and(|x| !x.is_balanced());
+        if unbalanced {
+            height += CHANNELS_ROW_HEIGHT;
+        }
+        height
+    }
+}
+
+/// The volume of every channel, with a balance slider if the node has left and right channels.
+fn channels_row(volume: &pipewire::Volume) -> Element<Message> {
+    let levels = volume
+        .channels
+        .iter()
+        .enumerate()
+        .map(|(index, channel)| {
+            let position = channel
+                .position
+                .clone()
+                .unwrap_or_else(|| (index + 1).to_string());
+            widget::text(format!("{position} {:.0}%", channel.volume * 100.0)).into()
+        })
+        .collect();
+    let balance = volume.balance().map(|balance| {
+        widget::row()
+            .align_y(Vertical::Center)
+            .spacing(4)
+            .width(iced::Length::Fill)
+            .push(widget::text("L"))
+            .push(
+                iced::widget::slider(-1.0..=1.0, balance, Message::ControlBalance)
+                    .step(BALANCE_STEP),
+            )
+            .push(widget::text("R"))
+    });
+
+    widget::row()
+        .align_y(Vertical::Center)
+        .spacing(8)
+        .push(widget::row::with_children(levels).spacing(8))
+        .push_maybe(balance)
+        .into()
+}
+
+/// The icon, name and volume of an application's stream, the volume can be changed here.
+fn stream_row(stream: &pipewire::Stream) -> Element<Message> {
+    let node_id = stream.node_id;
+    let control = move |control| Message::ControlStream { node_id, control };
+    let icon: Element<Message> = match &stream.icon_name {
+        Some(name) => widget::icon::from_name(name.as_str())
+            .size(28)
+            .icon()
+            .into(),
+        None => widget::text("").size(28).into(),
+    };
+    let mute = iced::widget::mouse_area(widget::text(if stream.mute { "" } else { "" }).size(28))
+        .on_press(control(VolumeControl::ToggleMute));
+    let name = widget::text(stream.application_name.as_deref().unwrap_or("Unknown"))
+        .width(iced::Length::Fixed(140.0));
+    let slider = iced::widget::slider(0.0..=MAX_VOLUME, stream.volume, move |x| {
+        control(VolumeControl::Set(x))
+    })
+    .step(VOLUME_STEP);
+
+    widget::row()
+        .align_y(Vertical::Center)
+        .spacing(8)
+        .push(icon)
+        .push(name)
+        .push(slider)
+        .push(widget::text(format!("{:.0}%", stream.volume * 100.0)))
+        .push(mute)
+        .into()
+}
+
+/// The shared look of every layer.
+fn snack<'a>(content: impl Into<Element<'a, Message>>) -> Element<'a, Message> {
+    widget::container(content)
         // .width(500)
         // .height(100)
         .padding(20)
@@ -277,18 +1328,41 @@ impl AppModel {
                 .border(iced::Border::default().rounded(20))
         })
         .into()
-    }
-    fn volume_status_view(&self) -> Element<Message> {
-        widget::text("Volume").into()
-    }
 }
 
 #[derive(Debug, Clone)]
 enum Message {
     UpdateMedia(UpdateMedia),
+    UpdateAudio(pipewire::Event),
+    UpdateBrightness(brightness::Event),
+    ControlVolume(VolumeControl),
+    ControlBrightness(BrightnessControl),
+    UpdateKeyboardBacklight(keyboard_backlight::KeyboardBacklight),
+    UpdatePower(power::Event),
+    UpdatePowerProfile(power_profiles::Event),
+    UpdateRadio(rfkill::Event),
+    UpdateBluetooth(bluetooth::Event),
+    UpdateNetwork(network::Event),
+    UpdateMount(udisks::Event),
+    /// Unmount the media of the mount layer and power its drive off.
+    Eject,
+    /// Switch to the next power profile.
+    CyclePowerProfile,
+    UpdateLockKey {
+        key: lock_keys::LockKey,
+        on: bool,
+    },
+    /// The new balance of the default sink, see [`pipewire::Volume::balance`].
+    ControlBalance(f32),
+    ControlStream {
+        node_id: u32,
+        control: VolumeControl,
+    },
+    ToggleMixer,
     Error(String),
     Clicked,
     OpenOrRefreshWindow,
+    Timeout,
     CloseWindow,
 }
 
@@ -298,6 +1372,19 @@ enum ShowingLayer {
     None,
     Media,
     Volume,
+    Microphone,
+    SwitchedNode,
+    /// The stream of an application, with the node id of the stream.
+    Stream(u32),
+    Brightness,
+    KeyboardBacklight,
+    LockKey,
+    Power,
+    PowerProfile,
+    Radio,
+    Bluetooth,
+    Network,
+    Mount,
 }
 
 #[derive(Debug, Clone)]
@@ -306,3 +1393,80 @@ enum UpdateMedia {
     Update(mpris::Properties),
     Remove(Vec<String>),
 }
+
+#[derive(Debug, Clone)]
+enum VolumeControl {
+    Scroll(ScrollDelta),
+    Set(f32),
+    ToggleMute,
+}
+
+impl VolumeControl {
+    /// Changes the local copy of the node's volume, returns the command that changes the node.
+    fn apply(self, node_id: u32, volume: &mut f32, mute: &mut bool) -> pipewire::Command {
+        match self {
+            Self::Scroll(delta) => {
+                let steps = match delta {
+                    ScrollDelta::Lines { y, .. } => y,
+                    ScrollDelta::Pixels { y, .. } => y / PIXELS_PER_SCROLL_LINE,
+                };
+                *volume = (*volume + steps * VOLUME_STEP).clamp(0.0, MAX_VOLUME);
+                pipewire::Command::SetVolume {
+                    node_id,
+                    volume: *volume,
+                }
+            }
+            Self::Set(level) => {
+                *volume = level.clamp(0.0, MAX_VOLUME);
+                pipewire::Command::SetVolume {
+                    node_id,
+                    volume: *volume,
+                }
+            }
+            Self::ToggleMute => {
+                *mute = !*mute;
+                pipewire::Command::SetMute {
+                    node_id,
+                    mute: *mute,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum BrightnessControl {
+    Scroll(ScrollDelta),
+    /// A position on the bar, in the scale of [`config::Brightness::curve`].
+    Set(f32),
+}
+
+impl BrightnessControl {
+    /// Changes the local copy of the backlight, returns the brightness to set.
+    fn apply(self, backlight: &mut brightness::Backlight, config: config::Brightness) -> u32 {
+        let position = config.curve.to_position(backlight.fraction());
+        let (position, direction) = match self {
+            Self::Scroll(delta) => {
+                let steps = match delta {
+                    ScrollDelta::Lines { y, .. } => y,
+                    ScrollDelta::Pixels { y, .. } => y / PIXELS_PER_SCROLL_LINE,
+                };
+                (position + steps * config.step, steps)
+            }
+            Self::Set(x) => (x, x - position),
+        };
+        let max = backlight.max_brightness;
+        let mut brightness = (config.curve.to_fraction(position) * max as f32).round() as u32;
+        // backlights with few levels would never move with small steps
+        if brightness == backlight.brightness {
+            if direction > 0.0 {
+                brightness = brightness.saturating_add(1);
+            } else if direction < 0.0 {
+                brightness = brightness.saturating_sub(1);
+            }
+        }
+        // a black screen is hard to get out of, `0` is off on many backlights
+        backlight.brightness = brightness.clamp(1.min(max), max);
+        backlight.brightness
+    }
+}
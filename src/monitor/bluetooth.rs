@@ -0,0 +1,389 @@
+use super::{Interfaces, Properties, get, subscribe};
+use cosmic::{
+    iced::futures::SinkExt,
+    iced_futures::futures::{TryStreamExt, channel::mpsc::Sender},
+};
+use std::collections::HashMap;
+use tokio::task::JoinHandle;
+use zbus::{Connection, MatchRule, Proxy, zvariant::OwnedObjectPath};
+
+const BLUEZ: &str = "org.bluez";
+const OBJECT_MANAGER_INTERFACE: &str = "org.freedesktop.DBus.ObjectManager";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Connected(Device),
+    Disconnected(Device),
+    /// The battery of a connected device changed, or it started to report one.
+    BatteryChanged(Device),
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Device {
+    /// The object path, e.g. "/org/bluez/hci0/dev_00_11_22_33_44_55".
+    pub path: String,
+    /// The name given by the user, or the name of the device.
+    pub alias: String,
+    /// A freedesktop icon name, e.g. "audio-headset".
+    pub icon: Option<String>,
+    pub connected: bool,
+    /// In percent, `None` if the device doesn't report it.
+    pub battery: Option<u8>,
+}
+
+impl Device {
+    fn update(&mut self, properties: &Properties) {
+        if let Some(alias) = get(properties, "Alias") {
+            self.alias = alias;
+        }
+        if let Some(icon) = get(properties, "Icon") {
+            self.icon = Some(icon);
+        }
+        if let Some(connected) = get(properties, "Connected") {
+            self.connected = connected;
+        }
+    }
+}
+
+/// Follows BlueZ on the system bus from a [`super::Registry`].
+pub struct BluetoothMonitor;
+
+impl super::Monitor for BluetoothMonitor {
+    type Event = Event;
+    type Error = zbus::Error;
+
+    fn name(&self) -> &'static str {
+        "bluetooth"
+    }
+    async fn start<T: Send + 'static>(
+        &self,
+        sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Result<JoinHandle<()>, zbus::Error> {
+        start_with_connection(sender, map, Connection::system().await?).await
+    }
+}
+
+/// Any bus with an `org.bluez` on it, e.g. a private bus with a stub in tests.
+async fn start_with_connection<T: Send + 'static>(
+    mut sender: Sender<T>,
+    map: impl Fn(Event) -> T + Send + Sync + 'static,
+    connection: Connection,
+) -> Result<JoinHandle<()>, zbus::Error> {
+    let rule = MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .sender(BLUEZ)?
+        .build();
+    let mut stream = subscribe(&connection, rule).await?;
+    let proxy = Proxy::new(&connection, BLUEZ, "/", OBJECT_MANAGER_INTERFACE).await?;
+    let objects: HashMap<OwnedObjectPath, Interfaces> =
+        proxy.call("GetManagedObjects", &()).await?;
+    let mut devices = HashMap::new();
+    for (path, interfaces) in objects {
+        add_interfaces(&mut devices, path.as_str(), &interfaces);
+    }
+
+    Ok(tokio::spawn(async move {
+        let mut send = async |event| {
+            if let Err(e) = sender.send(map(event)).await {
+                tracing::error!("Cannot send to sender: {e}");
+            }
+        };
+        loop {
+            let message = match stream.try_next().await {
+                Ok(Some(x)) => x,
+                Ok(None) => {
+                    tracing::info!("message stream ended");
+                    break;
+                }
+                Err(e) => {
+                    tracing::error!("error: {e}");
+                    break;
+                }
+            };
+            let header = message.header();
+            let body = message.body();
+            let event = match (
+                header.interface().map(|x| x.as_str()),
+                header.member().map(|x| x.as_str()),
+            ) {
+                (Some(OBJECT_MANAGER_INTERFACE), Some("InterfacesAdded")) => {
+                    match body.deserialize::<(OwnedObjectPath, Interfaces)>() {
+                        Ok((path, interfaces)) => {
+                            let before = devices.get(path.as_str()).cloned();
+                            add_interfaces(&mut devices, path.as_str(), &interfaces);
+                            compare(before.as_ref(), devices.get(path.as_str()))
+                        }
+                        Err(e) => Some(Event::Error(format!("deserialize error: {e}"))),
+                    }
+                }
+                (Some(OBJECT_MANAGER_INTERFACE), Some("InterfacesRemoved")) => {
+                    match body.deserialize::<(OwnedObjectPath, Vec<String>)>() {
+                        Ok((path, interfaces)) => {
+                            let before = devices.get(path.as_str()).cloned();
+                            for interface in interfaces {
+                                match interface.as_str() {
+                                    DEVICE_INTERFACE => {
+                                        devices.remove(path.as_str());
+                                    }
+                                    BATTERY_INTERFACE => {
+                                        if let Some(device) = devices.get_mut(path.as_str()) {
+                                            device.battery = None;
+                                        }
+                                    }
+                                    _ => (),
+                                }
+                            }
+                            // a removed device is gone, not disconnected, unless it was connected
+                            let after = devices.get(path.as_str()).cloned().or_else(|| {
+                                before.clone().map(|x| Device {
+                                    connected: false,
+                                    ..x
+                                })
+                            });
+                            compare(before.as_ref(), after.as_ref())
+                        }
+                        Err(e) => Some(Event::Error(format!("deserialize error: {e}"))),
+                    }
+                }
+                (Some(PROPERTIES_INTERFACE), Some("PropertiesChanged")) => {
+                    let Some(path) = header.path().map(|x| x.to_string()) else {
+                        continue;
+                    };
+                    match body.deserialize::<(String, Properties, Vec<String>)>() {
+                        Ok((interface, changed, invalidated)) => {
+                            let before = devices.get(&path).cloned();
+                            if let Some(device) = devices.get_mut(&path) {
+                                match interface.as_str() {
+                                    DEVICE_INTERFACE => device.update(&changed),
+                                    BATTERY_INTERFACE => {
+                                        if let Some(percentage) = get(&changed, "Percentage") {
+                                            device.battery = Some(percentage);
+                                        }
+                                        if invalidated.iter().any(|x| x == "Percentage") {
+                                            device.battery = None;
+                                        }
+                                    }
+                                    _ => (),
+                                }
+                            }
+                            compare(before.as_ref(), devices.get(&path))
+                        }
+                        Err(e) => Some(Event::Error(format!("deserialize error: {e}"))),
+                    }
+                }
+                _ => None,
+            };
+            if let Some(event) = event {
+                send(event).await;
+            }
+        }
+    }))
+}
+
+/// Adds the device and battery interfaces of the object at `path`, the battery comes with or
+/// after the device.
+fn add_interfaces(devices: &mut HashMap<String, Device>, path: &str, interfaces: &Interfaces) {
+    if let Some(properties) = interfaces.get(DEVICE_INTERFACE) {
+        let device = devices.entry(path.to_owned()).or_insert_with(|| Device {
+            path: path.to_owned(),
+            ..Default::default()
+        });
+        device.update(properties);
+    }
+    if let (Some(properties), Some(device)) =
+        (interfaces.get(BATTERY_INTERFACE), devices.get_mut(path))
+    {
+        device.battery = get(properties, "Percentage");
+    }
+}
+
+/// The event for a device going from `before` to `after`, `None` for a device that just
+/// appeared unless it is already connected.
+fn compare(before: Option<&Device>, after: Option<&Device>) -> Option<Event> {
+    let after = after?;
+    let was_connected = before.is_some_and(|x| x.connected);
+    match (was_connected, after.connected) {
+        (false, true) => Some(Event::Connected(after.clone())),
+        (true, false) => Some(Event::Disconnected(after.clone())),
+        (true, true) if before.is_some_and(|x| x.battery != after.battery) => {
+            Some(Event::BatteryChanged(after.clone()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::test_util::{PrivateBus, next},
+        *,
+    };
+    use cosmic::iced::futures::channel::mpsc::channel;
+    use zbus::fdo::ObjectManager;
+
+    struct Device1 {
+        alias: String,
+        connected: bool,
+    }
+
+    #[zbus::interface(name = "org.bluez.Device1")]
+    impl Device1 {
+        #[zbus(property)]
+        fn alias(&self) -> String {
+            self.alias.clone()
+        }
+        #[zbus(property)]
+        fn icon(&self) -> String {
+            "audio-headset".to_owned()
+        }
+        #[zbus(property)]
+        fn connected(&self) -> bool {
+            self.connected
+        }
+    }
+
+    struct Battery1 {
+        percentage: u8,
+    }
+
+    #[zbus::interface(name = "org.bluez.Battery1")]
+    impl Battery1 {
+        #[zbus(property)]
+        fn percentage(&self) -> u8 {
+            self.percentage
+        }
+    }
+
+    fn path(alias: &str) -> String {
+        format!("/org/bluez/hci0/dev_{alias}")
+    }
+
+    async fn add(bluez: &Connection, alias: &str, connected: bool) {
+        let device = Device1 {
+            alias: alias.to_owned(),
+            connected,
+        };
+        bluez.object_server().at(path(alias), device).await.unwrap();
+    }
+
+    async fn remove(bluez: &Connection, alias: &str) {
+        let server = bluez.object_server();
+        server.remove::<Device1, _>(path(alias)).await.unwrap();
+    }
+
+    async fn set_connected(bluez: &Connection, alias: &str, connected: bool) {
+        let stub = bluez
+            .object_server()
+            .interface::<_, Device1>(path(alias))
+            .await
+            .unwrap();
+        stub.get_mut().await.connected = connected;
+        let emitter = stub.signal_emitter();
+        stub.get().await.connected_changed(emitter).await.unwrap();
+    }
+
+    /// Adds, changes or removes the battery.
+    async fn set_battery(bluez: &Connection, alias: &str, percentage: Option<u8>) {
+        let server = bluez.object_server();
+        let Some(percentage) = percentage else {
+            server.remove::<Battery1, _>(path(alias)).await.unwrap();
+            return;
+        };
+        match server.interface::<_, Battery1>(path(alias)).await {
+            Ok(stub) => {
+                stub.get_mut().await.percentage = percentage;
+                let emitter = stub.signal_emitter();
+                stub.get().await.percentage_changed(emitter).await.unwrap();
+            }
+            Err(_) => {
+                let battery = Battery1 { percentage };
+                server.at(path(alias), battery).await.unwrap();
+            }
+        }
+    }
+
+    /// The kind of the event, with the alias, the connection and the battery of its device.
+    fn summary(event: Event) -> (&'static str, String, bool, Option<u8>) {
+        let (kind, device) = match event {
+            Event::Connected(x) => ("connected", x),
+            Event::Disconnected(x) => ("disconnected", x),
+            Event::BatteryChanged(x) => ("battery", x),
+            Event::Error(e) => panic!("{e}"),
+        };
+        (kind, device.alias, device.connected, device.battery)
+    }
+
+    #[tokio::test]
+    async fn devices_of_a_stub_bluez() {
+        let Some(bus) = PrivateBus::spawn() else {
+            return;
+        };
+        let bluez = bus.serve(BLUEZ, "/", ObjectManager).await;
+        add(&bluez, "headset", false).await;
+        add(&bluez, "mouse", true).await;
+        let (sender, mut receiver) = channel(10);
+        let handle = start_with_connection(sender, |x| x, bus.connect().await)
+            .await
+            .unwrap();
+        let mut next_summary = async || summary(next(&mut receiver).await);
+
+        // the mouse was connected before, a device appearing disconnected is nothing
+        add(&bluez, "keyboard", false).await;
+        set_connected(&bluez, "headset", true).await;
+        let expected = ("connected", "headset".to_owned(), true, None);
+        assert_eq!(next_summary().await, expected);
+
+        set_battery(&bluez, "headset", Some(80)).await;
+        let expected = ("battery", "headset".to_owned(), true, Some(80));
+        assert_eq!(next_summary().await, expected);
+        set_battery(&bluez, "headset", Some(75)).await;
+        let expected = ("battery", "headset".to_owned(), true, Some(75));
+        assert_eq!(next_summary().await, expected);
+        set_battery(&bluez, "headset", None).await;
+        let expected = ("battery", "headset".to_owned(), true, None);
+        assert_eq!(next_summary().await, expected);
+
+        add(&bluez, "speaker", true).await;
+        let expected = ("connected", "speaker".to_owned(), true, None);
+        assert_eq!(next_summary().await, expected);
+
+        // a disconnected device going away is nothing, a connected one disconnected
+        remove(&bluez, "keyboard").await;
+        remove(&bluez, "mouse").await;
+        let expected = ("disconnected", "mouse".to_owned(), false, None);
+        assert_eq!(next_summary().await, expected);
+
+        set_connected(&bluez, "headset", false).await;
+        let expected = ("disconnected", "headset".to_owned(), false, None);
+        assert_eq!(next_summary().await, expected);
+        handle.abort();
+    }
+
+    #[test]
+    fn compare_edge_cases() {
+        let device = |connected, battery| Device {
+            connected,
+            battery,
+            ..Default::default()
+        };
+        assert!(compare(None, None).is_none());
+        assert!(compare(Some(&device(true, None)), None).is_none());
+        assert!(compare(None, Some(&device(false, Some(50)))).is_none());
+        // a disconnected device reporting its battery isn't shown
+        assert!(compare(Some(&device(false, None)), Some(&device(false, Some(50)))).is_none());
+        assert!(compare(Some(&device(true, Some(50))), Some(&device(true, Some(50)))).is_none());
+        assert!(matches!(
+            compare(Some(&device(true, Some(50))), Some(&device(false, None))),
+            Some(Event::Disconnected(_))
+        ));
+        assert!(matches!(
+            compare(None, Some(&device(true, None))),
+            Some(Event::Connected(_))
+        ));
+    }
+}
@@ -0,0 +1,382 @@
+use cosmic::{iced::futures::SinkExt, iced_futures::futures::channel::mpsc::Sender};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    fs::{self, File},
+    io::{self, Read},
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+};
+use tokio::{io::unix::AsyncFd, task::JoinHandle};
+use zbus::{Connection, Proxy};
+
+const BACKLIGHT_CLASS: &str = "class/backlight";
+/// A backlight coming or going in the class directory.
+const CLASS_EVENTS: u32 =
+    libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO | libc::IN_ONLYDIR;
+/// A write to `brightness`, or the kernel notifying `actual_brightness`, which it also does when
+/// the firmware changes the brightness on its own, e.g. on a brightness key.
+const BACKLIGHT_EVENTS: u32 = libc::IN_CREATE | libc::IN_MODIFY | libc::IN_ONLYDIR;
+/// How steep [`Curve::Logarithmic`] is, the higher the more room for the dim levels.
+const LOGARITHMIC_CURVE: f32 = 100.0;
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Sent once logind is reachable, the controller changes the brightness.
+    Ready(Controller),
+    /// A backlight found on start or plugged in later, e.g. an external monitor.
+    Added(Backlight),
+    /// The brightness of a backlight changed.
+    Changed(Backlight),
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backlight {
+    /// The directory in `/sys/class/backlight`, e.g. "intel_backlight".
+    pub name: String,
+    pub brightness: u32,
+    pub max_brightness: u32,
+}
+
+impl Backlight {
+    /// From `0.0` to `1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.max_brightness == 0 {
+            return 0.0;
+        }
+        self.brightness as f32 / self.max_brightness as f32
+    }
+    pub(crate) fn read(path: &Path) -> io::Result<Self> {
+        let read_u32 = |file: &str| -> io::Result<u32> {
+            fs::read_to_string(path.join(file))?
+                .trim()
+                .parse()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        };
+        Ok(Self {
+            name: path
+                .file_name()
+                .map(|x| x.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            // what the hardware really uses, `brightness` is only what was asked
+            brightness: read_u32("actual_brightness").or_else(|_| read_u32("brightness"))?,
+            max_brightness: read_u32("max_brightness")?,
+        })
+    }
+}
+
+/// How a position on the brightness bar maps to the brightness of the backlight.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Curve {
+    Linear,
+    /// We see brightness logarithmically, the bar moves the same for every step we notice.
+    Logarithmic,
+}
+
+impl Curve {
+    /// The position on the bar of a [`Backlight::fraction`], both from `0.0` to `1.0`.
+    pub fn to_position(self, fraction: f32) -> f32 {
+        let fraction = fraction.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => fraction,
+            Self::Logarithmic => {
+                (1.0 + LOGARITHMIC_CURVE * fraction).ln() / (1.0 + LOGARITHMIC_CURVE).ln()
+            }
+        }
+    }
+    /// The inverse of [`Curve::to_position`].
+    pub fn to_fraction(self, position: f32) -> f32 {
+        let position = position.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => position,
+            Self::Logarithmic => {
+                ((1.0 + LOGARITHMIC_CURVE).powf(position) - 1.0) / LOGARITHMIC_CURVE
+            }
+        }
+    }
+}
+
+/// Changes the brightness with logind's `SetBrightness`, writing to sysfs needs root.
+#[derive(Debug, Clone)]
+pub struct Controller {
+    connection: Connection,
+}
+
+impl Controller {
+    /// Uses the system bus.
+    pub async fn new() -> zbus::Result<Self> {
+        Ok(Self::with_connection(Connection::system().await?))
+    }
+    /// Any bus with a logind on it, e.g. a private bus in tests.
+    pub fn with_connection(connection: Connection) -> Self {
+        Self { connection }
+    }
+    /// `name` is a [`Backlight::name`], `brightness` goes from `0` to
+    /// [`Backlight::max_brightness`].
+    pub async fn set(&self, name: &str, brightness: u32) -> zbus::Result<()> {
+        // `auto` is the session of the caller
+        let proxy = Proxy::new(
+            &self.connection,
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1/session/auto",
+            "org.freedesktop.login1.Session",
+        )
+        .await?;
+        proxy
+            .call("SetBrightness", &("backlight", name, brightness))
+            .await
+    }
+}
+
+/// Watches the backlights in `root` from a [`super::Registry`].
+pub struct BrightnessMonitor {
+    /// Usually [`super::SYSFS_ROOT`].
+    pub root: PathBuf,
+}
+
+impl super::Monitor for BrightnessMonitor {
+    type Event = Event;
+    type Error = io::Error;
+
+    fn name(&self) -> &'static str {
+        "brightness"
+    }
+    /// Watches the backlights in `<root>/class/backlight` with inotify, `root` is usually
+    /// [`super::SYSFS_ROOT`].
+    async fn start<T: Send + 'static>(
+        &self,
+        mut sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Result<JoinHandle<()>, io::Error> {
+        let class = self.root.join(BACKLIGHT_CLASS);
+        // only what fails on start is an error, later the backlights may come and go
+        let inotify = Inotify::new()?;
+        inotify.watch(&class, CLASS_EVENTS)?;
+
+        Ok(tokio::spawn(async move {
+            let mut send = async |event| {
+                if let Err(e) = sender.send(map(event)).await {
+                    tracing::error!("Cannot send to sender: {e}");
+                }
+            };
+            match Controller::new().await {
+                Ok(controller) => send(Event::Ready(controller)).await,
+                Err(e) => {
+                    tracing::warn!("cannot connect to logind, the brightness is read only: {e}")
+                }
+            }
+            let mut backlights = HashMap::<PathBuf, Backlight>::new();
+            loop {
+                let paths = match list(&class) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        send(Event::Error(format!("cannot list the backlights: {e}"))).await;
+                        break;
+                    }
+                };
+                backlights.retain(|path, _| paths.contains(path));
+                for path in paths {
+                    // watched before it is read so no change in between is lost, watching it
+                    // again is a no-op
+                    let watched = if backlights.contains_key(&path) {
+                        Ok(())
+                    } else {
+                        inotify.watch(&path, BACKLIGHT_EVENTS)
+                    };
+                    let backlight = match watched.and_then(|()| Backlight::read(&path)) {
+                        Ok(x) => x,
+                        // e.g. it is being added or removed, or it was never readable
+                        Err(e) => {
+                            if backlights.remove(&path).is_some() {
+                                tracing::warn!("cannot read the backlight {}: {e}", path.display());
+                            }
+                            continue;
+                        }
+                    };
+                    let event = match backlights.insert(path, backlight.clone()) {
+                        None => Event::Added(backlight),
+                        Some(previous) if previous != backlight => Event::Changed(backlight),
+                        Some(_) => continue,
+                    };
+                    send(event).await;
+                }
+                if let Err(e) = inotify.wait().await {
+                    send(Event::Error(format!("cannot watch the backlights: {e}"))).await;
+                    break;
+                }
+            }
+        }))
+    }
+}
+
+/// An inotify instance, non-blocking so the task can be aborted while it waits.
+struct Inotify(AsyncFd<File>);
+
+impl Inotify {
+    fn new() -> io::Result<Self> {
+        // SAFETY: no pointer is involved
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the descriptor was just opened and nothing else owns it
+        let file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+        Ok(Self(AsyncFd::new(file)?))
+    }
+    /// Follows symlinks, like the devices in a sysfs class.
+    fn watch(&self, path: &Path, mask: u32) -> io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        // SAFETY: `path` is a valid C string for the duration of the call
+        let watch = unsafe { libc::inotify_add_watch(self.0.as_raw_fd(), path.as_ptr(), mask) };
+        if watch < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    /// Waits for the next events and drops them, what is watched is read again instead. Cancel
+    /// safe.
+    async fn wait(&self) -> io::Result<()> {
+        // enough for a few events with names of `NAME_MAX` bytes
+        let mut buffer = [0; 4096];
+        loop {
+            let mut guard = self.0.readable().await?;
+            match guard.try_io(|x| x.get_ref().read(&mut buffer)) {
+                Ok(result) => return result.map(drop),
+                // woken up for nothing
+                Err(_) => continue,
+            }
+        }
+    }
+}
+
+/// The backlight directories in `class`, none if it is gone.
+fn list(class: &Path) -> io::Result<Vec<PathBuf>> {
+    match fs::read_dir(class) {
+        Ok(x) => x.map(|x| x.map(|x| x.path())).collect(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            Monitor,
+            test_util::{PrivateBus, next, write_in_place},
+        },
+        *,
+    };
+    use cosmic::iced::futures::channel::mpsc::{Receiver, channel};
+    use std::sync::{Arc, Mutex};
+
+    /// logind's session, it only knows `intel_backlight`.
+    #[derive(Default)]
+    struct Session {
+        calls: Arc<Mutex<Vec<(String, String, u32)>>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.login1.Session")]
+    impl Session {
+        fn set_brightness(
+            &self,
+            subsystem: String,
+            name: String,
+            brightness: u32,
+        ) -> zbus::fdo::Result<()> {
+            if name != "intel_backlight" {
+                return Err(zbus::fdo::Error::FileNotFound(name));
+            }
+            self.calls
+                .lock()
+                .unwrap()
+                .push((subsystem, name, brightness));
+            Ok(())
+        }
+    }
+
+    /// The next backlight and whether it was found on start.
+    async fn next_backlight(receiver: &mut Receiver<Event>) -> (bool, Backlight) {
+        loop {
+            match next(receiver).await {
+                // only with a logind around
+                Event::Ready(_) => continue,
+                Event::Added(x) => return (true, x),
+                Event::Changed(x) => return (false, x),
+                Event::Error(e) => panic!("{e}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn changes_of_a_fake_backlight() {
+        let root = tempfile::tempdir().unwrap();
+        let backlight = root.path().join(BACKLIGHT_CLASS).join("intel_backlight");
+        fs::create_dir_all(&backlight).unwrap();
+        fs::write(backlight.join("brightness"), "050\n").unwrap();
+        fs::write(backlight.join("max_brightness"), "200\n").unwrap();
+
+        let (sender, mut receiver) = channel(10);
+        let handle = BrightnessMonitor {
+            root: root.path().into(),
+        }
+        .start(sender, |x| x)
+        .await
+        .unwrap();
+
+        let (added, found) = next_backlight(&mut receiver).await;
+        assert!(added);
+        assert_eq!(found.name, "intel_backlight");
+        assert_eq!((found.brightness, found.max_brightness), (50, 200));
+        assert_eq!(found.fraction(), 0.25);
+
+        // the same value again isn't a change, so the next event is the one after it
+        write_in_place(&backlight.join("brightness"), "050\n");
+        write_in_place(&backlight.join("brightness"), "150\n");
+        let (added, changed) = next_backlight(&mut receiver).await;
+        assert!(!added);
+        assert_eq!(changed.brightness, 150);
+        assert_eq!(changed.fraction(), 0.75);
+
+        // a monitor plugged in after the start
+        let external = root.path().join(BACKLIGHT_CLASS).join("ddcci5");
+        fs::create_dir_all(&external).unwrap();
+        fs::write(external.join("max_brightness"), "100\n").unwrap();
+        fs::write(external.join("brightness"), "30\n").unwrap();
+        let (added, found) = next_backlight(&mut receiver).await;
+        assert!(added);
+        assert_eq!(found.name, "ddcci5");
+        assert_eq!(found.brightness, 30);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn controller_sets_the_brightness_through_logind() {
+        let Some(bus) = PrivateBus::spawn() else {
+            return;
+        };
+        let session = Session::default();
+        let calls = session.calls.clone();
+        let _logind = bus
+            .serve(
+                "org.freedesktop.login1",
+                "/org/freedesktop/login1/session/auto",
+                session,
+            )
+            .await;
+        let controller = Controller::with_connection(bus.connect().await);
+
+        controller.set("intel_backlight", 120).await.unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [("backlight".to_owned(), "intel_backlight".to_owned(), 120)]
+        );
+        assert!(controller.set("acpi_video0", 1).await.is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use super::brightness::Backlight;
+use cosmic::{
+    iced::futures::SinkExt,
+    iced_futures::futures::{TryStreamExt, channel::mpsc::Sender},
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+use zbus::{Connection, MatchRule, Proxy};
+
+const UPOWER: &str = "org.freedesktop.UPower";
+const KBD_BACKLIGHT_PATH: &str = "/org/freedesktop/UPower/KbdBacklight";
+const KBD_BACKLIGHT_INTERFACE: &str = "org.freedesktop.UPower.KbdBacklight";
+const LEDS_CLASS: &str = "class/leds";
+const KBD_BACKLIGHT_SUFFIX: &str = "::kbd_backlight";
+/// The firmware changes the brightness itself on Fn+Space, that doesn't wake up inotify so sysfs
+/// is polled.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Not sent for the brightness found on start.
+    Changed(KeyboardBacklight),
+    Error(String),
+}
+
+/// Keyboard backlights only have a few levels, `0` is off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyboardBacklight {
+    pub brightness: u32,
+    pub max_brightness: u32,
+}
+
+/// Follows the keyboard backlight from a [`super::Registry`].
+pub struct KeyboardBacklightMonitor {
+    /// Where the sysfs fallback looks, usually [`super::SYSFS_ROOT`].
+    pub root: PathBuf,
+}
+
+impl super::Monitor for KeyboardBacklightMonitor {
+    type Event = Event;
+    type Error = zbus::Error;
+
+    fn name(&self) -> &'static str {
+        "keyboard_backlight"
+    }
+    /// Follows UPower's `KbdBacklight`, or `<root>/class/leds/*::kbd_backlight` without UPower.
+    async fn start<T: Send + 'static>(
+        &self,
+        mut sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Result<JoinHandle<()>, zbus::Error> {
+        let send = async move |event| {
+            if let Err(e) = sender.send(map(event)).await {
+                tracing::error!("Cannot send to sender: {e}");
+            }
+        };
+        let upower = match Connection::system().await {
+            Ok(connection) => monitor_upower(send.clone(), connection).await,
+            Err(e) => Err(e),
+        };
+        match upower {
+            Ok(monitor) => Ok(tokio::spawn(monitor)),
+            Err(e) => {
+                tracing::info!("no keyboard backlight in upower ({e}), watching sysfs");
+                let monitor = monitor_sysfs(send, &self.root)?;
+                Ok(tokio::spawn(monitor))
+            }
+        }
+    }
+}
+
+async fn monitor_upower(
+    mut send: impl AsyncFnMut(Event) -> () + Clone + Send,
+    connection: Connection,
+) -> Result<impl Future<Output = ()>, zbus::Error> {
+    let rule = MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .sender(UPOWER)?
+        .path(KBD_BACKLIGHT_PATH)?
+        .interface(KBD_BACKLIGHT_INTERFACE)?
+        .build();
+    let mut stream = super::subscribe(&connection, rule).await?;
+    let proxy = Proxy::new(
+        &connection,
+        UPOWER,
+        KBD_BACKLIGHT_PATH,
+        KBD_BACKLIGHT_INTERFACE,
+    )
+    .await?;
+    // fails if there is no keyboard backlight
+    let max_brightness: i32 = proxy.call("GetMaxBrightness", &()).await?;
+    let brightness: i32 = proxy.call("GetBrightness", &()).await?;
+    let mut current = KeyboardBacklight {
+        brightness: brightness.max(0) as u32,
+        max_brightness: max_brightness.max(0) as u32,
+    };
+
+    Ok(async move {
+        loop {
+            let message = match stream.try_next().await {
+                Ok(Some(x)) => x,
+                Ok(None) => {
+                    tracing::info!("message stream ended");
+                    break;
+                }
+                Err(e) => {
+                    tracing::error!("error: {e}");
+                    break;
+                }
+            };
+            let body = message.body();
+            // newer UPower sends both signals for every change
+            let brightness = match message.header().member().map(|x| x.as_str()) {
+                Some("BrightnessChanged") => body.deserialize::<i32>(),
+                Some("BrightnessChangedWithSource") => {
+                    body.deserialize::<(i32, String)>().map(|x| x.0)
+                }
+                _ => continue,
+            };
+            match brightness {
+                Ok(brightness) => {
+                    let brightness = brightness.max(0) as u32;
+                    if brightness != current.brightness {
+                        current.brightness = brightness;
+                        send(Event::Changed(current)).await;
+                    }
+                }
+                Err(e) => send(Event::Error(format!("deserialize error: {e}"))).await,
+            }
+        }
+    })
+}
+
+fn monitor_sysfs(
+    mut send: impl AsyncFnMut(Event) -> () + Send,
+    root: &Path,
+) -> Result<impl Future<Output = ()>, io::Error> {
+    let path = fs::read_dir(root.join(LEDS_CLASS))?
+        .filter_map(|x| x.ok())
+        .map(|x| x.path())
+        .find(|x| {
+            x.file_name()
+                .is_some_and(|x| x.to_string_lossy().ends_with(KBD_BACKLIGHT_SUFFIX))
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no keyboard backlight"))?;
+    let mut current = Backlight::read(&path)?;
+
+    Ok(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            match Backlight::read(&path) {
+                Ok(backlight) if backlight == current => (),
+                Ok(backlight) => {
+                    current = backlight;
+                    send(Event::Changed(KeyboardBacklight {
+                        brightness: current.brightness,
+                        max_brightness: current.max_brightness,
+                    }))
+                    .await;
+                }
+                // e.g. the keyboard was unplugged
+                Err(e) => {
+                    send(Event::Error(format!("cannot read {}: {e}", path.display()))).await;
+                    break;
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::test_util::{PrivateBus, next, write_in_place},
+        *,
+    };
+    use cosmic::iced::futures::channel::mpsc::{Receiver, channel};
+    use zbus::object_server::SignalEmitter;
+
+    /// UPower's keyboard backlight, the brightness is only changed by the test.
+    struct KbdBacklight {
+        brightness: i32,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.UPower.KbdBacklight")]
+    impl KbdBacklight {
+        fn get_max_brightness(&self) -> i32 {
+            3
+        }
+        fn get_brightness(&self) -> i32 {
+            self.brightness
+        }
+        #[zbus(signal)]
+        async fn brightness_changed(emitter: &SignalEmitter<'_>, value: i32) -> zbus::Result<()>;
+        #[zbus(signal)]
+        async fn brightness_changed_with_source(
+            emitter: &SignalEmitter<'_>,
+            value: i32,
+            source: &str,
+        ) -> zbus::Result<()>;
+    }
+
+    fn events() -> (impl AsyncFnMut(Event) + Clone + Send, Receiver<Event>) {
+        let (mut sender, receiver) = channel(10);
+        let send = async move |event| sender.send(event).await.unwrap();
+        (send, receiver)
+    }
+
+    async fn next_brightness(receiver: &mut Receiver<Event>) -> KeyboardBacklight {
+        match next(receiver).await {
+            Event::Changed(x) => x,
+            Event::Error(e) => panic!("{e}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn changes_in_upower() {
+        let Some(bus) = PrivateBus::spawn() else {
+            return;
+        };
+        let upower = bus
+            .serve(UPOWER, KBD_BACKLIGHT_PATH, KbdBacklight { brightness: 1 })
+            .await;
+        let (send, mut receiver) = events();
+        let handle = tokio::spawn(monitor_upower(send, bus.connect().await).await.unwrap());
+
+        // neither the brightness found on start nor the second signal newer UPower sends for the
+        // same change are changes
+        let emitter = SignalEmitter::new(&upower, KBD_BACKLIGHT_PATH).unwrap();
+        KbdBacklight::brightness_changed(&emitter, 1).await.unwrap();
+        KbdBacklight::brightness_changed(&emitter, 2).await.unwrap();
+        KbdBacklight::brightness_changed_with_source(&emitter, 2, "internal")
+            .await
+            .unwrap();
+        KbdBacklight::brightness_changed_with_source(&emitter, 0, "external")
+            .await
+            .unwrap();
+        let changed = next_brightness(&mut receiver).await;
+        assert_eq!(changed.brightness, 2);
+        assert_eq!(changed.max_brightness, 3);
+        assert_eq!(next_brightness(&mut receiver).await.brightness, 0);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn without_upower() {
+        let Some(bus) = PrivateBus::spawn() else {
+            return;
+        };
+        let (send, _receiver) = events();
+        assert!(monitor_upower(send, bus.connect().await).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn changes_of_a_fake_keyboard_backlight() {
+        let root = tempfile::tempdir().unwrap();
+        let leds = root.path().join(LEDS_CLASS);
+        // the lock keys are LEDs too
+        fs::create_dir_all(leds.join("input3::capslock")).unwrap();
+        let backlight = leds.join("tpacpi::kbd_backlight");
+        fs::create_dir_all(&backlight).unwrap();
+        fs::write(backlight.join("brightness"), "0\n").unwrap();
+        fs::write(backlight.join("max_brightness"), "2\n").unwrap();
+
+        let (send, mut receiver) = events();
+        let handle = tokio::spawn(monitor_sysfs(send, root.path()).unwrap());
+
+        // the same value again isn't a change, so the next event is the one after it
+        write_in_place(&backlight.join("brightness"), "0\n");
+        write_in_place(&backlight.join("brightness"), "2\n");
+        let changed = next_brightness(&mut receiver).await;
+        assert_eq!(changed.brightness, 2);
+        assert_eq!(changed.max_brightness, 2);
+
+        // unplugged
+        fs::remove_dir_all(&backlight).unwrap();
+        let Event::Error(_) = next(&mut receiver).await else {
+            panic!("not an error");
+        };
+        handle.abort();
+    }
+
+    #[test]
+    fn no_keyboard_backlight() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join(LEDS_CLASS).join("input3::capslock")).unwrap();
+        let (send, _receiver) = events();
+        let error = monitor_sysfs(send, root.path()).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,173 @@
+use cosmic::{iced::futures::SinkExt, iced_futures::futures::channel::mpsc::Sender};
+use std::{collections::HashMap, fs, io, path::PathBuf, time::Duration};
+use tokio::task::JoinHandle;
+
+const LEDS_CLASS: &str = "class/leds";
+/// The kernel changes the LEDs itself, that doesn't wake up inotify so they are polled.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Not sent for the state found on start.
+    Changed { key: LockKey, on: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LockKey {
+    Caps,
+    Num,
+    Scroll,
+}
+
+impl LockKey {
+    const ALL: [Self; 3] = [Self::Caps, Self::Num, Self::Scroll];
+
+    /// The end of the LED names, e.g. `input3::capslock`.
+    fn led_suffix(self) -> &'static str {
+        match self {
+            Self::Caps => "::capslock",
+            Self::Num => "::numlock",
+            Self::Scroll => "::scrolllock",
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Caps => "Caps Lock",
+            Self::Num => "Num Lock",
+            Self::Scroll => "Scroll Lock",
+        }
+    }
+}
+
+/// Polls the lock key LEDs in `root` from a [`super::Registry`].
+pub struct LockKeysMonitor {
+    /// Usually [`super::SYSFS_ROOT`].
+    pub root: PathBuf,
+}
+
+impl super::Monitor for LockKeysMonitor {
+    type Event = Event;
+    type Error = io::Error;
+
+    fn name(&self) -> &'static str {
+        "lock_keys"
+    }
+    /// Watches the `input*::capslock`, `::numlock` and `::scrolllock` LEDs in
+    /// `<root>/class/leds`, `root` is usually [`super::SYSFS_ROOT`].
+    ///
+    /// Every keyboard has its own LEDs, they all follow the same lock state so a key is on if any
+    /// of its LEDs is on. Keyboards plugged in later are not followed.
+    async fn start<T: Send + 'static>(
+        &self,
+        mut sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Result<JoinHandle<()>, io::Error> {
+        let mut leds = Vec::new();
+        for entry in fs::read_dir(self.root.join(LEDS_CLASS))? {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .map(|x| x.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let Some(key) = LockKey::ALL
+                .into_iter()
+                .find(|key| name.starts_with("input") && name.ends_with(key.led_suffix()))
+            else {
+                continue;
+            };
+            leds.push((key, path));
+        }
+        let mut states = HashMap::new();
+        for key in LockKey::ALL {
+            if let Some(on) = read_state(key, &leds) {
+                states.insert(key, on);
+            }
+        }
+
+        Ok(tokio::spawn(async move {
+            let mut send = async |event| {
+                if let Err(e) = sender.send(map(event)).await {
+                    tracing::error!("Cannot send to sender: {e}");
+                }
+            };
+            if leds.is_empty() {
+                tracing::info!("no lock key LED");
+                return;
+            }
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                for key in LockKey::ALL {
+                    let Some(on) = read_state(key, &leds) else {
+                        continue;
+                    };
+                    if states.insert(key, on) != Some(on) {
+                        send(Event::Changed { key, on }).await;
+                    }
+                }
+            }
+        }))
+    }
+}
+
+/// `None` if no LED of `key` can be read.
+fn read_state(key: LockKey, leds: &[(LockKey, PathBuf)]) -> Option<bool> {
+    let mut state = None;
+    for (_, path) in leds.iter().filter(|(x, _)| *x == key) {
+        let brightness = fs::read_to_string(path.join("brightness")).ok();
+        let Some(brightness) = brightness.and_then(|x| x.trim().parse::<u32>().ok()) else {
+            continue;
+        };
+        state = Some(state.unwrap_or(false) || brightness > 0);
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            Monitor,
+            test_util::{next, write_in_place},
+        },
+        *,
+    };
+    use cosmic::iced::futures::channel::mpsc::channel;
+
+    #[tokio::test]
+    async fn changes_of_fake_leds() {
+        let root = tempfile::tempdir().unwrap();
+        let leds = root.path().join(LEDS_CLASS);
+        for (name, brightness) in [
+            ("input3::capslock", "0\n"),
+            ("input3::numlock", "1\n"),
+            // a second keyboard
+            ("input9::capslock", "0\n"),
+            ("input9::numlock", "1\n"),
+            // not a keyboard, never read
+            ("platform::capslock", "1\n"),
+        ] {
+            fs::create_dir_all(leds.join(name)).unwrap();
+            fs::write(leds.join(name).join("brightness"), brightness).unwrap();
+        }
+
+        let (sender, mut receiver) = channel(10);
+        let handle = LockKeysMonitor {
+            root: root.path().into(),
+        }
+        .start(sender, |x| x)
+        .await
+        .unwrap();
+
+        // Num Lock found on is not sent, Caps Lock is on once any keyboard has it on
+        write_in_place(&leds.join("input9::capslock/brightness"), "1\n");
+        let Event::Changed { key, on } = next(&mut receiver).await;
+        assert_eq!((key, on), (LockKey::Caps, true));
+
+        write_in_place(&leds.join("input3::numlock/brightness"), "0\n");
+        write_in_place(&leds.join("input9::numlock/brightness"), "0\n");
+        let Event::Changed { key, on } = next(&mut receiver).await;
+        assert_eq!((key, on), (LockKey::Num, false));
+        handle.abort();
+    }
+}
@@ -1,2 +1,140 @@
+use crate::config::Config;
+use cosmic::iced_futures::futures::channel::mpsc::Sender;
+use std::{collections::HashMap, error::Error, pin::Pin, sync::Arc};
+use tokio::task::JoinHandle;
+use zbus::{Connection, MatchRule, MessageStream, zvariant::OwnedValue};
+
+pub mod bluetooth;
+pub mod brightness;
+pub mod keyboard_backlight;
+pub mod lock_keys;
 pub mod mpris;
+pub mod network;
 pub mod pipewire;
+pub mod power;
+pub mod power_profiles;
+pub mod rfkill;
+#[cfg(test)]
+mod test_util;
+pub mod udisks;
+
+/// Where the real sysfs is, the monitors reading it take a directory with the same layout in tests.
+pub const SYSFS_ROOT: &str = "/sys";
+
+/// The properties of a D-Bus interface, by name.
+type Properties = HashMap<String, OwnedValue>;
+/// The properties of every interface of an object, by interface name.
+type Interfaces = HashMap<String, Properties>;
+
+type StartFuture =
+    Pin<Box<dyn Future<Output = Result<JoinHandle<()>, Box<dyn Error + Send + Sync>>> + Send>>;
+
+/// A source of events, started by a [`Registry`].
+pub trait Monitor: Send + Sync + 'static {
+    type Event: Send + 'static;
+    type Error: Into<Box<dyn Error + Send + Sync>>;
+
+    /// The key of the monitor in [`Config::monitors`], e.g. "pipewire".
+    fn name(&self) -> &'static str;
+    /// Spawns the task sending the events, the error is only for what fails on start.
+    fn start<T: Send + 'static>(
+        &self,
+        sender: Sender<T>,
+        map: impl Fn(Self::Event) -> T + Clone + Send + Sync + 'static,
+    ) -> impl Future<Output = Result<JoinHandle<()>, Self::Error>> + Send;
+    /// Stops what [`Monitor::start`] spawned, aborting the task by default.
+    fn stop(&self, handle: JoinHandle<()>) {
+        handle.abort();
+    }
+}
+
+/// The monitors with how their events become `T`, stopped on drop.
+pub struct Registry<T> {
+    monitors: Vec<Registered<T>>,
+}
+
+struct Registered<T> {
+    name: &'static str,
+    start: Box<dyn Fn(Sender<T>) -> StartFuture + Send + Sync>,
+    stop: Box<dyn Fn(JoinHandle<()>) + Send + Sync>,
+    /// `Some` while running.
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self {
+            monitors: Vec::new(),
+        }
+    }
+}
+
+impl<T: Send + 'static> Registry<T> {
+    pub fn register<M: Monitor>(
+        mut self,
+        monitor: M,
+        map: impl Fn(M::Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Self {
+        let monitor = Arc::new(monitor);
+        let start = {
+            let monitor = monitor.clone();
+            move |sender| {
+                let monitor = monitor.clone();
+                let map = map.clone();
+                Box::pin(async move { monitor.start(sender, map).await.map_err(Into::into) })
+                    as StartFuture
+            }
+        };
+        self.monitors.push(Registered {
+            name: monitor.name(),
+            start: Box::new(start),
+            stop: Box::new(move |handle| monitor.stop(handle)),
+            handle: None,
+        });
+        self
+    }
+    /// Starts the monitors enabled in `config` one after the other, a monitor that cannot start
+    /// is skipped, e.g. its service isn't installed.
+    pub async fn start(&mut self, sender: Sender<T>, config: &Config) {
+        for monitor in &mut self.monitors {
+            if monitor.handle.is_some() {
+                continue;
+            }
+            if !config.is_enabled(monitor.name) {
+                tracing::info!("the {} monitor is disabled", monitor.name);
+                continue;
+            }
+            match (monitor.start)(sender.clone()).await {
+                Ok(handle) => monitor.handle = Some(handle),
+                Err(e) => tracing::info!("the {} monitor is not available: {e}", monitor.name),
+            }
+        }
+    }
+}
+
+impl<T> Registry<T> {
+    pub fn stop(&mut self) {
+        for monitor in &mut self.monitors {
+            if let Some(handle) = monitor.handle.take() {
+                (monitor.stop)(handle);
+            }
+        }
+    }
+}
+
+impl<T> Drop for Registry<T> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The signals matching `rule`. The D-Bus monitors subscribe before reading the state of the
+/// service, the signals sent meanwhile wait in the stream so no change is lost in between.
+async fn subscribe(connection: &Connection, rule: MatchRule<'_>) -> zbus::Result<MessageStream> {
+    MessageStream::for_match_rule(rule, connection, None).await
+}
+
+/// The property `key`, `None` if it is missing or of another type.
+fn get<T: TryFrom<OwnedValue>>(properties: &Properties, key: &str) -> Option<T> {
+    properties.get(key)?.try_clone().ok()?.try_into().ok()
+}
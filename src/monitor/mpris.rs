@@ -44,20 +44,32 @@ pub enum LoopStatus {
     Playlist,
 }
 
-pub async fn start<T: Send + 'static>(
-    mut sender: Sender<T>,
-    map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
-) -> Result<JoinHandle<()>, zbus::Error> {
-    let send = async move |event| {
-        if let Err(e) = sender.send(map(event)).await {
-            tracing::error!("Cannot send to sender: {e}");
-        }
-    };
-    let monitor_method_call = monitor_method_call(send.clone()).await?;
-    let monitor_properties_change = monitor_properties_change(send).await?;
-    Ok(tokio::spawn(async {
-        tokio::join!(monitor_method_call, monitor_properties_change,);
-    }))
+/// Follows the MPRIS players on the session bus from a [`super::Registry`].
+pub struct MprisMonitor;
+
+impl super::Monitor for MprisMonitor {
+    type Event = Event;
+    type Error = zbus::Error;
+
+    fn name(&self) -> &'static str {
+        "mpris"
+    }
+    async fn start<T: Send + 'static>(
+        &self,
+        mut sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Result<JoinHandle<()>, zbus::Error> {
+        let send = async move |event| {
+            if let Err(e) = sender.send(map(event)).await {
+                tracing::error!("Cannot send to sender: {e}");
+            }
+        };
+        let monitor_method_call = monitor_method_call(send.clone()).await?;
+        let monitor_properties_change = monitor_properties_change(send).await?;
+        Ok(tokio::spawn(async {
+            tokio::join!(monitor_method_call, monitor_properties_change,);
+        }))
+    }
 }
 
 async fn monitor_method_call(
@@ -0,0 +1,541 @@
+use super::{Properties, get, subscribe};
+use cosmic::{
+    iced::futures::SinkExt,
+    iced_futures::futures::{TryStreamExt, channel::mpsc::Sender},
+};
+use std::collections::HashMap;
+use tokio::task::JoinHandle;
+use zbus::{Connection, MatchRule, Proxy, zvariant::OwnedObjectPath};
+
+const NETWORK_MANAGER: &str = "org.freedesktop.NetworkManager";
+const NETWORK_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager";
+const ACTIVE_CONNECTION_INTERFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+/// `NM_ACTIVE_CONNECTION_STATE_ACTIVATED`.
+const ACTIVATED: u32 = 2;
+/// `NM_ACTIVE_CONNECTION_STATE_DEACTIVATED`.
+const DEACTIVATED: u32 = 4;
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A connection was activated, e.g. a VPN, [`Status::connection`] is that connection.
+    Connected(Status),
+    /// A connection was deactivated, e.g. the Wi-Fi dropped, [`Status::connection`] is that
+    /// connection.
+    Disconnected(Status),
+    /// [`Status::connection`] is the primary connection, `None` without one.
+    ConnectivityChanged(Status),
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+    pub connection: Option<ActiveConnection>,
+    pub connectivity: Connectivity,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveConnection {
+    /// The name of the connection, e.g. the SSID of a Wi-Fi.
+    pub id: String,
+    pub kind: ConnectionKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionKind {
+    Wired,
+    Wireless,
+    Vpn,
+    Mobile,
+    Bluetooth,
+    Other,
+}
+
+impl ConnectionKind {
+    /// The `Type` of an active connection, a setting name like "802-11-wireless".
+    fn from_type(value: &str, vpn: bool) -> Self {
+        match value {
+            _ if vpn => Self::Vpn,
+            "802-3-ethernet" => Self::Wired,
+            "802-11-wireless" => Self::Wireless,
+            "vpn" | "wireguard" => Self::Vpn,
+            "gsm" | "cdma" => Self::Mobile,
+            "bluetooth" => Self::Bluetooth,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// NetworkManager's `NMConnectivityState`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Connectivity {
+    #[default]
+    Unknown,
+    None,
+    /// Behind a captive portal, e.g. the sign in page of a hotel.
+    Portal,
+    Limited,
+    Full,
+}
+
+impl From<u32> for Connectivity {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Self::None,
+            2 => Self::Portal,
+            3 => Self::Limited,
+            4 => Self::Full,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// The part of NetworkManager the events are made of.
+struct State {
+    /// By the object path of the active connection, with its `State`.
+    connections: HashMap<String, (ActiveConnection, u32)>,
+    /// The object path of the primary connection, "/" without one.
+    primary: String,
+    connectivity: Connectivity,
+}
+
+impl State {
+    fn status(&self, path: &str) -> Status {
+        Status {
+            connection: self.connections.get(path).map(|x| x.0.clone()),
+            connectivity: self.connectivity,
+        }
+    }
+}
+
+/// Follows NetworkManager on the system bus from a [`super::Registry`].
+pub struct NetworkMonitor;
+
+impl super::Monitor for NetworkMonitor {
+    type Event = Event;
+    type Error = zbus::Error;
+
+    fn name(&self) -> &'static str {
+        "network"
+    }
+    async fn start<T: Send + 'static>(
+        &self,
+        sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Result<JoinHandle<()>, zbus::Error> {
+        start_with_connection(sender, map, Connection::system().await?).await
+    }
+}
+
+/// Any bus with an `org.freedesktop.NetworkManager` on it, e.g. a private bus with a stub in
+/// tests.
+async fn start_with_connection<T: Send + 'static>(
+    mut sender: Sender<T>,
+    map: impl Fn(Event) -> T + Send + Sync + 'static,
+    connection: Connection,
+) -> Result<JoinHandle<()>, zbus::Error> {
+    let rule = MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .sender(NETWORK_MANAGER)?
+        .build();
+    let mut stream = subscribe(&connection, rule).await?;
+    let proxy = Proxy::new(
+        &connection,
+        NETWORK_MANAGER,
+        NETWORK_MANAGER_PATH,
+        NETWORK_MANAGER,
+    )
+    .await?;
+    let active_connections: Vec<OwnedObjectPath> = proxy.get_property("ActiveConnections").await?;
+    let mut state = State {
+        connections: HashMap::new(),
+        primary: proxy
+            .get_property::<OwnedObjectPath>("PrimaryConnection")
+            .await?
+            .to_string(),
+        connectivity: proxy.get_property::<u32>("Connectivity").await?.into(),
+    };
+    for path in active_connections {
+        match read_connection(&connection, path.as_str()).await {
+            Ok(x) => {
+                state.connections.insert(path.to_string(), x);
+            }
+            // it may be gone already
+            Err(e) => tracing::warn!("cannot read the active connection {}: {e}", path.as_str()),
+        }
+    }
+
+    Ok(tokio::spawn(async move {
+        let mut send = async |event| {
+            if let Err(e) = sender.send(map(event)).await {
+                tracing::error!("Cannot send to sender: {e}");
+            }
+        };
+        loop {
+            let message = match stream.try_next().await {
+                Ok(Some(x)) => x,
+                Ok(None) => {
+                    tracing::info!("message stream ended");
+                    break;
+                }
+                Err(e) => {
+                    tracing::error!("error: {e}");
+                    break;
+                }
+            };
+            let header = message.header();
+            let Some(path) = header.path().map(|x| x.to_string()) else {
+                continue;
+            };
+            let body = message.body();
+            let event = match (
+                header.interface().map(|x| x.as_str()),
+                header.member().map(|x| x.as_str()),
+            ) {
+                // the overall state, the connectivity may come with it
+                (Some(NETWORK_MANAGER), Some("StateChanged")) => {
+                    match refresh(&connection, &mut state).await {
+                        Ok(event) => event,
+                        Err(e) => Some(Event::Error(format!(
+                            "cannot read the NetworkManager state: {e}"
+                        ))),
+                    }
+                }
+                (Some(ACTIVE_CONNECTION_INTERFACE), Some("StateChanged")) => {
+                    match body.deserialize::<(u32, u32)>() {
+                        Ok((active_state, _reason)) => {
+                            active_state_changed(&connection, &mut state, &path, active_state).await
+                        }
+                        Err(e) => Some(Event::Error(format!("deserialize error: {e}"))),
+                    }
+                }
+                (Some(PROPERTIES_INTERFACE), Some("PropertiesChanged"))
+                    if path == NETWORK_MANAGER_PATH =>
+                {
+                    match body.deserialize::<(String, Properties, Vec<String>)>() {
+                        Ok((interface, changed, _)) if interface == NETWORK_MANAGER => {
+                            let primary = get::<OwnedObjectPath>(&changed, "PrimaryConnection");
+                            let connectivity = get::<u32>(&changed, "Connectivity");
+                            update(
+                                &connection,
+                                &mut state,
+                                primary.map(|x| x.to_string()),
+                                connectivity.map(Connectivity::from),
+                            )
+                            .await
+                        }
+                        Ok(_) => None,
+                        Err(e) => Some(Event::Error(format!("deserialize error: {e}"))),
+                    }
+                }
+                _ => None,
+            };
+            if let Some(event) = event {
+                send(event).await;
+            }
+        }
+    }))
+}
+
+/// The `Id`, `Type` and `State` of the active connection at `path`.
+async fn read_connection(
+    connection: &Connection,
+    path: &str,
+) -> zbus::Result<(ActiveConnection, u32)> {
+    let proxy = Proxy::new(
+        connection,
+        NETWORK_MANAGER,
+        path,
+        ACTIVE_CONNECTION_INTERFACE,
+    )
+    .await?;
+    let id: String = proxy.get_property("Id").await?;
+    let kind: String = proxy.get_property("Type").await?;
+    let vpn: bool = proxy.get_property("Vpn").await?;
+    let active_state: u32 = proxy.get_property("State").await?;
+    Ok((
+        ActiveConnection {
+            id,
+            kind: ConnectionKind::from_type(&kind, vpn),
+        },
+        active_state,
+    ))
+}
+
+async fn active_state_changed(
+    connection: &Connection,
+    state: &mut State,
+    path: &str,
+    active_state: u32,
+) -> Option<Event> {
+    if !state.connections.contains_key(path) {
+        // a new activation, its properties are read once, the state read may already be the one
+        // of the signal so it starts unknown
+        match read_connection(connection, path).await {
+            Ok((active_connection, _)) => {
+                state
+                    .connections
+                    .insert(path.to_owned(), (active_connection, 0));
+            }
+            Err(e) => {
+                return Some(Event::Error(format!(
+                    "cannot read the active connection {path}: {e}"
+                )));
+            }
+        }
+    }
+    let (_, previous) = state.connections.get_mut(path)?;
+    if std::mem::replace(previous, active_state) == active_state {
+        return None;
+    }
+    match active_state {
+        ACTIVATED => Some(Event::Connected(state.status(path))),
+        DEACTIVATED => {
+            let event = Event::Disconnected(state.status(path));
+            // NetworkManager removes the object right after
+            state.connections.remove(path);
+            Some(event)
+        }
+        _ => None,
+    }
+}
+
+/// Reads `PrimaryConnection` and `Connectivity` again.
+async fn refresh(connection: &Connection, state: &mut State) -> zbus::Result<Option<Event>> {
+    let proxy = Proxy::new(
+        connection,
+        NETWORK_MANAGER,
+        NETWORK_MANAGER_PATH,
+        NETWORK_MANAGER,
+    )
+    .await?;
+    let primary: OwnedObjectPath = proxy.get_property("PrimaryConnection").await?;
+    let connectivity: u32 = proxy.get_property("Connectivity").await?;
+    Ok(update(
+        connection,
+        state,
+        Some(primary.to_string()),
+        Some(connectivity.into()),
+    )
+    .await)
+}
+
+/// [`Event::ConnectivityChanged`] if the connectivity changed, the primary connection alone
+/// changes with every activation that already has its own event.
+async fn update(
+    connection: &Connection,
+    state: &mut State,
+    primary: Option<String>,
+    connectivity: Option<Connectivity>,
+) -> Option<Event> {
+    if let Some(primary) = primary {
+        if primary != "/" && !state.connections.contains_key(&primary) {
+            // its activation may still come, as in `active_state_changed`
+            match read_connection(connection, &primary).await {
+                Ok((active_connection, _)) => {
+                    state
+                        .connections
+                        .insert(primary.clone(), (active_connection, 0));
+                }
+                Err(e) => tracing::warn!("cannot read the primary connection {primary}: {e}"),
+            }
+        }
+        state.primary = primary;
+    }
+    let connectivity = connectivity?;
+    if std::mem::replace(&mut state.connectivity, connectivity) == connectivity {
+        return None;
+    }
+    Some(Event::ConnectivityChanged(state.status(&state.primary)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::test_util::{PrivateBus, next},
+        *,
+    };
+    use cosmic::iced::futures::channel::mpsc::channel;
+    use zbus::object_server::SignalEmitter;
+
+    const WIRED_PATH: &str = "/org/freedesktop/NetworkManager/ActiveConnection/1";
+    const VPN_PATH: &str = "/org/freedesktop/NetworkManager/ActiveConnection/2";
+
+    struct NetworkManager {
+        primary: OwnedObjectPath,
+        connectivity: u32,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager")]
+    impl NetworkManager {
+        #[zbus(property)]
+        fn active_connections(&self) -> Vec<OwnedObjectPath> {
+            vec![self.primary.clone()]
+        }
+        #[zbus(property)]
+        fn primary_connection(&self) -> OwnedObjectPath {
+            self.primary.clone()
+        }
+        #[zbus(property)]
+        fn connectivity(&self) -> u32 {
+            self.connectivity
+        }
+        #[zbus(signal)]
+        async fn state_changed(emitter: &SignalEmitter<'_>, state: u32) -> zbus::Result<()>;
+    }
+
+    struct Active {
+        id: String,
+        kind: String,
+        state: u32,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+    impl Active {
+        #[zbus(property)]
+        fn id(&self) -> String {
+            self.id.clone()
+        }
+        #[zbus(property, name = "Type")]
+        fn kind(&self) -> String {
+            self.kind.clone()
+        }
+        #[zbus(property)]
+        fn vpn(&self) -> bool {
+            self.kind == "vpn"
+        }
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            self.state
+        }
+        /// Named apart from the `state_changed` of the property.
+        #[zbus(signal, name = "StateChanged")]
+        async fn state_signal(
+            emitter: &SignalEmitter<'_>,
+            state: u32,
+            reason: u32,
+        ) -> zbus::Result<()>;
+    }
+
+    async fn add(network_manager: &Connection, path: &str, id: &str, kind: &str, state: u32) {
+        let active = Active {
+            id: id.to_owned(),
+            kind: kind.to_owned(),
+            state,
+        };
+        let server = network_manager.object_server();
+        server.at(path, active).await.unwrap();
+    }
+
+    async fn set_active_state(network_manager: &Connection, path: &str, state: u32) {
+        let stub = network_manager
+            .object_server()
+            .interface::<_, Active>(path)
+            .await
+            .unwrap();
+        stub.get_mut().await.state = state;
+        Active::state_signal(stub.signal_emitter(), state, 0)
+            .await
+            .unwrap();
+    }
+
+    /// Sends `PropertiesChanged` or, with `state_changed`, only the `StateChanged` NetworkManager
+    /// sends along.
+    async fn set_network(
+        network_manager: &Connection,
+        primary: &str,
+        connectivity: u32,
+        state_changed: bool,
+    ) {
+        let stub = network_manager
+            .object_server()
+            .interface::<_, NetworkManager>(NETWORK_MANAGER_PATH)
+            .await
+            .unwrap();
+        let emitter = stub.signal_emitter();
+        let mut network = stub.get_mut().await;
+        network.primary = OwnedObjectPath::try_from(primary).unwrap();
+        network.connectivity = connectivity;
+        drop(network);
+        if state_changed {
+            NetworkManager::state_changed(emitter, 70).await.unwrap();
+            return;
+        }
+        let network = stub.get().await;
+        network.primary_connection_changed(emitter).await.unwrap();
+        network.connectivity_changed(emitter).await.unwrap();
+    }
+
+    fn connection(id: &str, kind: ConnectionKind) -> Option<ActiveConnection> {
+        Some(ActiveConnection {
+            id: id.to_owned(),
+            kind,
+        })
+    }
+
+    #[tokio::test]
+    async fn changes_of_a_stub_network_manager() {
+        let Some(bus) = PrivateBus::spawn() else {
+            return;
+        };
+        let network = NetworkManager {
+            primary: OwnedObjectPath::try_from(WIRED_PATH).unwrap(),
+            connectivity: 4,
+        };
+        let network_manager = bus
+            .serve(NETWORK_MANAGER, NETWORK_MANAGER_PATH, network)
+            .await;
+        add(
+            &network_manager,
+            WIRED_PATH,
+            "Wired",
+            "802-3-ethernet",
+            ACTIVATED,
+        )
+        .await;
+        let (sender, mut receiver) = channel(10);
+        let handle = start_with_connection(sender, |x| x, bus.connect().await)
+            .await
+            .unwrap();
+
+        // the activating state read first is not the one of the signal
+        add(&network_manager, VPN_PATH, "Office", "vpn", 1).await;
+        set_active_state(&network_manager, VPN_PATH, ACTIVATED).await;
+        let Event::Connected(status) = next(&mut receiver).await else {
+            panic!("not connected");
+        };
+        assert_eq!(status.connection, connection("Office", ConnectionKind::Vpn));
+        assert_eq!(status.connectivity, Connectivity::Full);
+
+        set_network(&network_manager, WIRED_PATH, 2, false).await;
+        let Event::ConnectivityChanged(status) = next(&mut receiver).await else {
+            panic!("not a connectivity change");
+        };
+        assert_eq!(
+            status.connection,
+            connection("Wired", ConnectionKind::Wired)
+        );
+        assert_eq!(status.connectivity, Connectivity::Portal);
+        set_network(&network_manager, WIRED_PATH, 4, true).await;
+        let Event::ConnectivityChanged(status) = next(&mut receiver).await else {
+            panic!("not a connectivity change");
+        };
+        assert_eq!(status.connectivity, Connectivity::Full);
+
+        set_active_state(&network_manager, WIRED_PATH, DEACTIVATED).await;
+        let Event::Disconnected(status) = next(&mut receiver).await else {
+            panic!("not disconnected");
+        };
+        assert_eq!(
+            status.connection,
+            connection("Wired", ConnectionKind::Wired)
+        );
+        set_network(&network_manager, "/", 1, false).await;
+        let Event::ConnectivityChanged(status) = next(&mut receiver).await else {
+            panic!("not a connectivity change");
+        };
+        assert_eq!(status.connection, None);
+        assert_eq!(status.connectivity, Connectivity::None);
+        handle.abort();
+    }
+}
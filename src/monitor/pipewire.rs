@@ -1,88 +1,1591 @@
-use cosmic::iced::futures::{SinkExt, channel::{mpsc::Sender, oneshot}, executor};
-use pipewire::context::Context;
-use pipewire::main_loop::MainLoop;
-use pipewire::types::ObjectType;
+use cosmic::iced::futures::{SinkExt, channel::mpsc::Sender};
+use pipewire::{
+    context::Context,
+    core::Core,
+    keys,
+    main_loop::MainLoop,
+    metadata::{self, MetadataListener},
+    node::{self, NodeListener},
+    properties::Properties,
+    registry::{GlobalObject, Registry},
+    spa::{
+        self,
+        param::ParamType,
+        param::audio::{AudioFormat, AudioInfoRaw},
+        pod::{
+            Object, Pod, Property, Value, ValueArray,
+            deserialize::PodDeserializer,
+            serialize::{GenError, PodSerializer},
+        },
+        utils::{Direction, SpaTypes, dict::DictRef},
+    },
+    stream::{StreamFlags, StreamListener},
+    types::ObjectType,
+};
+use serde::Deserialize;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fmt,
+    io::{self, Cursor},
+    rc::{Rc, Weak},
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
 use tokio::task::JoinHandle;
 
-pub async fn start<T: Send + 'static>(
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Sent for the nodes found on start too, like the devices and the metadata.
+    NodeAdded(Node),
+    /// A node is gone, e.g. the stream of an application.
+    NodeRemoved(u32),
+    DeviceAdded(Device),
+    DeviceRemoved(u32),
+    MetadataAdded(Metadata),
+    MetadataRemoved(u32),
+    /// A param of a node changed, only the params listed in [`SUBSCRIBED_PARAMS`] are reported.
+    ParamChanged {
+        id: u32,
+        param: ParamType,
+    },
+    /// The `node.name` of a default node, `None` if there is no such default node.
+    DefaultNodeChanged {
+        kind: DefaultNode,
+        name: Option<String>,
+    },
+    /// The default node is now another node, not sent for the default nodes found on start.
+    DefaultNodeSwitched(SwitchedNode),
+    /// The volume or the mute state of a default node changed.
+    Volume(Volume),
+    /// The volume or the mute state of an application's playback stream changed, use
+    /// [`Event::NodeRemoved`] to know when it is gone.
+    StreamVolume(Stream),
+    /// How loud the default sink is, only while the meter runs, see [`Command::StartMeter`].
+    Level(Level),
+    /// Sent each time the PipeWire thread (re)connects, the controller sends [`Command`]s to it.
+    /// The state reported before a reconnection is stale, everything is sent again after this.
+    Ready(Controller),
+    Error(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// `volume` is in the same scale as [`Volume::volume`].
+    SetVolume {
+        node_id: u32,
+        volume: f32,
+    },
+    SetMute {
+        node_id: u32,
+        mute: bool,
+    },
+    /// One volume per channel, in the order of [`Volume::channels`] and the scale of
+    /// [`Volume::volume`].
+    SetChannelVolumes {
+        node_id: u32,
+        volumes: Vec<f32>,
+    },
+    /// Starts capturing the monitor ports of the default sink to send [`Event::Level`]s, only
+    /// the target is updated if it already runs.
+    StartMeter,
+    StopMeter,
+    /// Plays the sample on the default sink, ignored if the last one started less than
+    /// [`FEEDBACK_INTERVAL`] ago.
+    PlayFeedback(Arc<Sample>),
+}
+
+/// What the UI sends to the PipeWire thread.
+enum Request {
+    Command(Command),
+    Shutdown,
+}
+
+#[derive(Clone)]
+pub struct Controller {
+    requests: pipewire::channel::Sender<Request>,
+    shutdown: Shutdown,
+}
+
+impl Controller {
+    pub fn send(&self, command: Command) {
+        if let Err(Request::Command(command)) = self.requests.send(Request::Command(command)) {
+            tracing::error!("pipewire thread is gone, cannot run {command:?}");
+        }
+    }
+    /// Stops the PipeWire thread, it won't reconnect anymore.
+    pub fn shutdown(&self) {
+        self.shutdown.request();
+    }
+}
+
+/// Stops the PipeWire thread, whether it is connected or waiting to reconnect.
+#[derive(Clone, Default)]
+struct Shutdown(Arc<(Mutex<ShutdownState>, Condvar)>);
+
+#[derive(Default)]
+struct ShutdownState {
+    requested: bool,
+    /// The requests of the current connection, `None` between connections.
+    requests: Option<pipewire::channel::Sender<Request>>,
+}
+
+impl Shutdown {
+    fn request(&self) {
+        let (state, condvar) = &*self.0;
+        let mut state = state.lock().unwrap();
+        state.requested = true;
+        if let Some(requests) = state.requests.take() {
+            let _ = requests.send(Request::Shutdown);
+        }
+        condvar.notify_all();
+    }
+    /// Keeps `requests` to stop the connection, returns `false` if it shouldn't run at all.
+    fn connected(&self, requests: pipewire::channel::Sender<Request>) -> bool {
+        let mut state = self.0.0.lock().unwrap();
+        state.requests = Some(requests);
+        !state.requested
+    }
+    fn disconnected(&self) {
+        self.0.0.lock().unwrap().requests = None;
+    }
+    /// Waits for `timeout` before reconnecting, returns `false` if a shutdown is requested.
+    fn wait(&self, timeout: Duration) -> bool {
+        let (state, condvar) = &*self.0;
+        let state = state.lock().unwrap();
+        let (state, _) = condvar
+            .wait_timeout_while(state, timeout, |x| !x.requested)
+            .unwrap();
+        !state.requested
+    }
+}
+
+impl fmt::Debug for Controller {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Controller").finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Node {
+    pub id: u32,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub media_class: Option<String>,
+    pub device_id: Option<u32>,
+    pub icon_name: Option<String>,
+    pub form_factor: Option<String>,
+    pub application_name: Option<String>,
+    pub application_icon_name: Option<String>,
+    /// The channel positions of `audio.position`, e.g. `["FL", "FR"]`.
+    pub positions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Device {
+    pub id: u32,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub icon_name: Option<String>,
+    pub form_factor: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    pub id: u32,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchedNode {
+    pub kind: DefaultNode,
+    pub name: String,
+    /// The `node.description`, e.g. "WH-1000XM4".
+    pub description: Option<String>,
+    /// `device.icon-name` of the node or its device, e.g. "audio-headphones-bluetooth".
+    pub icon_name: Option<String>,
+    /// `device.form-factor` of the node or its device, e.g. "headphone".
+    pub form_factor: Option<String>,
+}
+
+/// A playback stream of an application.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stream {
+    pub node_id: u32,
+    /// `application.name`, e.g. "Firefox".
+    pub application_name: Option<String>,
+    /// `application.icon-name`, e.g. "firefox".
+    pub icon_name: Option<String>,
+    /// Same scale as [`Volume::volume`].
+    pub volume: f32,
+    pub mute: bool,
+}
+
+/// Levels of the samples played since the last [`Event::Level`], `1.0` is full scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Level {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+/// Interleaved `f32` samples.
+#[derive(Debug)]
+pub struct Sample {
+    pub rate: u32,
+    pub channels: u32,
+    pub samples: Vec<f32>,
+}
+
+/// The default nodes we follow, their names are kept in the `default` metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefaultNode {
+    Sink,
+    Source,
+}
+
+impl DefaultNode {
+    const ALL: [Self; 2] = [Self::Sink, Self::Source];
+
+    fn metadata_key(self) -> &'static str {
+        match self {
+            Self::Sink => "default.audio.sink",
+            Self::Source => "default.audio.source",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Volume {
+    pub kind: DefaultNode,
+    pub node_id: u32,
+    /// The `node.description` of the node, e.g. "Headphones".
+    pub description: Option<String>,
+    /// Same scale as `wpctl get-volume`, `1.0` is 100%.
+    pub volume: f32,
+    pub mute: bool,
+    pub channels: Vec<Channel>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    /// The position from `audio.position`, e.g. "FL", `None` if the node doesn't say.
+    pub position: Option<String>,
+    /// Same scale as [`Volume::volume`].
+    pub volume: f32,
+}
+
+const SUBSCRIBED_PARAMS: &[ParamType] = &[ParamType::Props];
+const DEFAULT_METADATA_NAME: &str = "default";
+const INITIAL_ROUNDTRIPS: u8 = 2;
+const STREAM_MEDIA_CLASS: &str = "Stream/Output/Audio";
+const AUDIO_POSITION: &str = "audio.position";
+/// Channel volumes closer than this are considered equal.
+const BALANCE_TOLERANCE: f32 = 0.005;
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// How often the queued events are delivered to the UI, about once per frame.
+const DELIVERY_INTERVAL: Duration = Duration::from_millis(16);
+/// The meter sends at most one [`Event::Level`] per interval.
+const METER_INTERVAL: Duration = Duration::from_millis(50);
+const METER_NAME: &str = "snacks-level-meter";
+const TARGET_OBJECT: &str = "target.object";
+const FEEDBACK_NAME: &str = "snacks-feedback";
+const FEEDBACK_INTERVAL: Duration = Duration::from_millis(100);
+
+impl Node {
+    fn new(id: u32, props: Option<&DictRef>) -> Self {
+        let get = |key: &str| props.and_then(|x| x.get(key)).map(str::to_owned);
+        Self {
+            id,
+            name: get(*keys::NODE_NAME),
+            description: get(*keys::NODE_DESCRIPTION),
+            media_class: get(*keys::MEDIA_CLASS),
+            device_id: props
+                .and_then(|x| x.get(*keys::DEVICE_ID))
+                .and_then(|x| x.parse().ok()),
+            icon_name: get(*keys::DEVICE_ICON_NAME),
+            form_factor: get(*keys::DEVICE_FORM_FACTOR),
+            application_name: get(*keys::APP_NAME),
+            application_icon_name: get(*keys::APP_ICON_NAME),
+            positions: props
+                .and_then(|x| x.get(AUDIO_POSITION))
+                .map(parse_positions)
+                .unwrap_or_default(),
+        }
+    }
+    fn is_stream(&self) -> bool {
+        // our own feedback sound isn't an application
+        self.media_class.as_deref() == Some(STREAM_MEDIA_CLASS)
+            && self.name.as_deref() != Some(FEEDBACK_NAME)
+    }
+}
+
+impl Device {
+    fn new(id: u32, props: Option<&DictRef>) -> Self {
+        let get = |key: &str| props.and_then(|x| x.get(key)).map(str::to_owned);
+        Self {
+            id,
+            name: get(*keys::DEVICE_NAME),
+            description: get(*keys::DEVICE_DESCRIPTION),
+            icon_name: get(*keys::DEVICE_ICON_NAME),
+            form_factor: get(*keys::DEVICE_FORM_FACTOR),
+        }
+    }
+}
+
+impl Metadata {
+    fn new(id: u32, props: Option<&DictRef>) -> Self {
+        Self {
+            id,
+            name: props
+                .and_then(|x| x.get("metadata.name"))
+                .map(str::to_owned),
+        }
+    }
+}
+
+impl Volume {
+    /// `true` if every channel has the same volume.
+    pub fn is_balanced(&self) -> bool {
+        let volumes = self.channels.iter().map(|x| x.volume);
+        let min = volumes.clone().fold(f32::INFINITY, f32::min);
+        let max = volumes.fold(0.0, f32::max);
+        self.channels.is_empty() || max - min < BALANCE_TOLERANCE
+    }
+    /// From `-1.0`, only the left channels, to `1.0`, only the right ones, like pavucontrol.
+    /// `None` if the node has no left or no right channel.
+    pub fn balance(&self) -> Option<f32> {
+        let left = self.side_volume(Side::Left)?;
+        let right = self.side_volume(Side::Right)?;
+        Some(if left == right {
+            0.0
+        } else if left > right {
+            right / left - 1.0
+        } else {
+            1.0 - left / right
+        })
+    }
+    /// The channel volumes with `balance` applied, the louder side keeps its volume and the
+    /// channels that are neither left nor right are unchanged.
+    pub fn channel_volumes_with_balance(&self, balance: f32) -> Vec<f32> {
+        let balance = balance.clamp(-1.0, 1.0);
+        let loudest = self
+            .side_volume(Side::Left)
+            .into_iter()
+            .chain(self.side_volume(Side::Right))
+            .fold(0.0, f32::max);
+        self.channels
+            .iter()
+            .map(|channel| match Side::of(channel.position.as_deref()) {
+                Some(Side::Left) => loudest * (1.0 - balance.max(0.0)),
+                Some(Side::Right) => loudest * (1.0 + balance.min(0.0)),
+                None => channel.volume,
+            })
+            .collect()
+    }
+    fn side_volume(&self, side: Side) -> Option<f32> {
+        self.channels
+            .iter()
+            .filter(|x| Side::of(x.position.as_deref()) == Some(side))
+            .map(|x| x.volume)
+            .reduce(f32::max)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    /// `FL`, `RL`, `SL`, `FLC`, ... are on the left, `LFE` and the center ones are on no side.
+    fn of(position: Option<&str>) -> Option<Self> {
+        match position? {
+            "LFE" | "LFE2" => None,
+            x if x.ends_with('L') || x.starts_with("FL") => Some(Self::Left),
+            x if x.ends_with('R') || x.starts_with("FR") => Some(Self::Right),
+            _ => None,
+        }
+    }
+}
+
+/// `audio.position` looks like `FL,FR` or `[ FL FR ]`.
+fn parse_positions(value: &str) -> Vec<String> {
+    value
+        .split(|x: char| x == ',' || x == '[' || x == ']' || x.is_whitespace())
+        .filter(|x| !x.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Connects to PipeWire from a [`super::Registry`], stopping it shuts the PipeWire thread down.
+///
+/// The PipeWire thread keeps trying to connect until it does, each connection is reported with an
+/// [`Event::Ready`].
+#[derive(Default)]
+pub struct PipeWireMonitor {
+    /// The `remote.name` to connect to, the default daemon of the session if `None`.
+    pub remote: Option<String>,
+    /// `Some` while started.
+    shutdown: Mutex<Option<Shutdown>>,
+}
+
+impl super::Monitor for PipeWireMonitor {
+    type Event = Event;
+    type Error = pipewire::Error;
+
+    fn name(&self) -> &'static str {
+        "pipewire"
+    }
+    fn start<T: Send + 'static>(
+        &self,
+        sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> impl Future<Output = Result<JoinHandle<()>, pipewire::Error>> + Send {
+        let shutdown = Shutdown::default();
+        *self.shutdown.lock().unwrap() = Some(shutdown.clone());
+        let remote = self.remote.clone();
+        async move { Ok(spawn(sender, map, remote, shutdown)) }
+    }
+    fn stop(&self, handle: JoinHandle<()>) {
+        if let Some(shutdown) = self.shutdown.lock().unwrap().take() {
+            shutdown.request();
+        }
+        handle.abort();
+    }
+}
+
+/// The task delivering the events, it ends with the PipeWire thread.
+fn spawn<T: Send + 'static>(
     sender: Sender<T>,
-    map: impl Fn(String) -> T + Clone + Send + Sync + 'static,
-) -> Result<JoinHandle<()>, pipewire::Error> {
-    let (tx, rx) = oneshot::channel();
-
-    let handle = tokio::task::spawn_blocking(move || {
-        let mainloop = match MainLoop::new(None) {
-            Ok(x) => x,
-            Err(e) => {
-                tx.send(e).unwrap();
-                return;
+    map: impl Fn(Event) -> T + Send + Sync + 'static,
+    remote: Option<String>,
+    shutdown: Shutdown,
+) -> JoinHandle<()> {
+    let mailbox = Arc::new(Mailbox::default());
+    let thread = tokio::task::spawn_blocking({
+        let mailbox = mailbox.clone();
+        move || run_with_reconnect(&mailbox, remote.as_deref(), &shutdown)
+    });
+    tokio::spawn(async move {
+        deliver(mailbox, sender, map).await;
+        if let Err(e) = thread.await {
+            tracing::error!("the pipewire thread panicked: {e}");
+        }
+    })
+}
+
+/// Runs [`run`] again whenever the connection ends, until the UI stops listening or a shutdown is
+/// requested.
+fn run_with_reconnect(mailbox: &Arc<Mailbox>, remote: Option<&str>, shutdown: &Shutdown) {
+    let _close = CloseOnDrop(mailbox.clone());
+    let mut connected = false;
+    let mut backoff = MIN_RECONNECT_DELAY;
+    loop {
+        match run(mailbox, remote, shutdown, &mut connected) {
+            Ok(()) => backoff = MIN_RECONNECT_DELAY,
+            // the first failure is shown, e.g. PipeWire isn't running yet on login
+            Err(e) if !connected && backoff == MIN_RECONNECT_DELAY => {
+                mailbox.push(Event::Error(format!("cannot connect to pipewire: {e}")));
             }
-        };
-        let context = match Context::new(&mainloop) {
-            Ok(x) => x,
-            Err(e) => {
-                tx.send(e).unwrap();
-                return;
+            Err(e) => tracing::warn!("cannot connect to pipewire: {e}"),
+        }
+        if mailbox.is_closed() || !shutdown.wait(backoff) {
+            return;
+        }
+        tracing::info!("reconnecting to pipewire");
+        backoff = (backoff * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+/// Connects to PipeWire and runs the main loop until the daemon goes away, the UI stops listening
+/// or a shutdown is requested.
+///
+/// `connected` is set once connected, it stays set across reconnections.
+fn run(
+    mailbox: &Arc<Mailbox>,
+    remote: Option<&str>,
+    shutdown: &Shutdown,
+    connected: &mut bool,
+) -> Result<(), pipewire::Error> {
+    let mainloop = MainLoop::new(None)?;
+    let context = Context::new(&mainloop)?;
+    let core = context.connect(remote.map(|remote| {
+        let mut props = Properties::new();
+        props.insert(*keys::REMOTE_NAME, remote);
+        props
+    }))?;
+    let registry = Rc::new(core.get_registry()?);
+    *connected = true;
+
+    let weak_mainloop = mainloop.downgrade();
+    let send: SendEvent = Rc::new({
+        let mailbox = mailbox.clone();
+        move |event| {
+            if !mailbox.push(event) {
+                if let Some(mainloop) = weak_mainloop.upgrade() {
+                    mainloop.quit();
+                }
             }
-        };
-        let core = match context.connect(None) {
-            Ok(x) => x,
-            Err(e) => {
-                tx.send(e).unwrap();
-                return;
+        }
+    });
+    let state = Rc::new(RefCell::new(State::default()));
+
+    // everything we get before the second roundtrip is the initial state, the first one
+    // gives us the globals and the second one the properties of the objects we bound
+    let pending_sync = Rc::new(RefCell::new((INITIAL_ROUNDTRIPS, core.sync(0))));
+    let _core_listener = core
+        .add_listener_local()
+        .done({
+            let core = core.clone();
+            let state = Rc::downgrade(&state);
+            move |id, seq| {
+                let mut pending_sync = pending_sync.borrow_mut();
+                let (remaining, pending) = &mut *pending_sync;
+                if id != pipewire::core::PW_ID_CORE || pending.as_ref().ok() != Some(&seq) {
+                    return;
+                }
+                *remaining -= 1;
+                if *remaining > 0 {
+                    *pending = core.sync(0);
+                } else if let Some(state) = state.upgrade() {
+                    state.borrow_mut().initialized = true;
+                }
             }
-        };
-        let registry = match core.get_registry() {
-            Ok(x) => x,
-            Err(e) => {
-                tx.send(e).unwrap();
+        })
+        .error({
+            let send = send.clone();
+            let weak_mainloop = mainloop.downgrade();
+            move |id, _seq, res, message| {
+                // the daemon closed the connection, `start` reconnects once the loop returns
+                let broken_pipe =
+                    io::Error::from_raw_os_error(-res).kind() == io::ErrorKind::BrokenPipe;
+                if id == pipewire::core::PW_ID_CORE && broken_pipe {
+                    tracing::warn!("disconnected from pipewire: {message}");
+                    if let Some(mainloop) = weak_mainloop.upgrade() {
+                        mainloop.quit();
+                    }
+                } else {
+                    send(Event::Error(format!("pipewire error on {id}: {message}")));
+                }
+            }
+        })
+        .register();
+
+    let (requests, receiver) = pipewire::channel::channel();
+    let _requests = receiver.attach(mainloop.loop_(), {
+        let core = core.clone();
+        let send = send.clone();
+        let state = Rc::downgrade(&state);
+        let weak_mainloop = mainloop.downgrade();
+        move |request| match request {
+            Request::Command(command) => {
+                let Some(state) = state.upgrade() else {
+                    return;
+                };
+                if let Err(e) = state.borrow_mut().run(&core, &send, command) {
+                    send(Event::Error(e));
+                }
+            }
+            Request::Shutdown => {
+                if let Some(mainloop) = weak_mainloop.upgrade() {
+                    mainloop.quit();
+                }
+            }
+        }
+    });
+    if !shutdown.connected(requests.clone()) {
+        return Ok(());
+    }
+    send(Event::Ready(Controller {
+        requests,
+        shutdown: shutdown.clone(),
+    }));
+
+    let _listener = registry
+        .add_listener_local()
+        .global({
+            let send = send.clone();
+            let state = Rc::downgrade(&state);
+            let registry = Rc::downgrade(&registry);
+            move |global| {
+                let (Some(state), Some(registry)) = (state.upgrade(), registry.upgrade()) else {
+                    return;
+                };
+                let props = global.props;
+                let event = match &global.type_ {
+                    ObjectType::Node => {
+                        let node = Node::new(global.id, props);
+                        match BoundNode::bind(
+                            &registry,
+                            global,
+                            node.clone(),
+                            Rc::downgrade(&state),
+                            send.clone(),
+                        ) {
+                            Ok(bound) => {
+                                state.borrow_mut().nodes.insert(global.id, bound);
+                            }
+                            Err(e) => {
+                                send(Event::Error(format!("cannot bind node {}: {e}", global.id)))
+                            }
+                        }
+                        Event::NodeAdded(node)
+                    }
+                    ObjectType::Device => {
+                        let device = Device::new(global.id, props);
+                        state.borrow_mut().devices.insert(global.id, device.clone());
+                        Event::DeviceAdded(device)
+                    }
+                    ObjectType::Metadata => {
+                        let metadata = Metadata::new(global.id, props);
+                        if metadata.name.as_deref() == Some(DEFAULT_METADATA_NAME) {
+                            match BoundMetadata::bind(
+                                &registry,
+                                global,
+                                Rc::downgrade(&state),
+                                send.clone(),
+                            ) {
+                                Ok(bound) => {
+                                    state.borrow_mut().default_metadata = Some(bound);
+                                }
+                                Err(e) => send(Event::Error(format!(
+                                    "cannot bind the default metadata {}: {e}",
+                                    global.id
+                                ))),
+                            }
+                        }
+                        Event::MetadataAdded(metadata)
+                    }
+                    _ => return,
+                };
+                state
+                    .borrow_mut()
+                    .globals
+                    .insert(global.id, global.type_.clone());
+                send(event);
+            }
+        })
+        .global_remove({
+            let state = Rc::downgrade(&state);
+            move |id| {
+                let Some(state) = state.upgrade() else {
+                    return;
+                };
+                let mut state = state.borrow_mut();
+                let event = match state.globals.remove(&id) {
+                    Some(ObjectType::Node) => {
+                        state.nodes.remove(&id);
+                        for default in state.defaults.values_mut() {
+                            if default.volume.as_ref().map(|x| x.node_id) == Some(id) {
+                                default.volume = None;
+                            }
+                        }
+                        Event::NodeRemoved(id)
+                    }
+                    Some(ObjectType::Device) => {
+                        state.devices.remove(&id);
+                        Event::DeviceRemoved(id)
+                    }
+                    Some(ObjectType::Metadata) => {
+                        if state.default_metadata.as_ref().map(|x| x.id) == Some(id) {
+                            state.default_metadata = None;
+                        }
+                        Event::MetadataRemoved(id)
+                    }
+                    _ => return,
+                };
+                drop(state);
+                send(event);
+            }
+        })
+        .register();
+    mainloop.run();
+    shutdown.disconnected();
+    Ok(())
+}
+
+type SendEvent = Rc<dyn Fn(Event)>;
+
+/// Events waiting for the UI, so the PipeWire thread never waits for it.
+///
+/// Only the latest value of an event that is a state (e.g. a volume) is kept, a burst of param
+/// changes is coalesced into one update per [`DELIVERY_INTERVAL`].
+#[derive(Default)]
+struct Mailbox {
+    queue: Mutex<Queue>,
+    notify: tokio::sync::Notify,
+}
+
+#[derive(Default)]
+struct Queue {
+    events: VecDeque<Event>,
+    /// Either the UI stopped listening or the PipeWire thread is gone.
+    closed: bool,
+}
+
+/// Events with the same key replace each other.
+#[derive(PartialEq)]
+enum MailboxKey {
+    Param { id: u32, param: ParamType },
+    Volume(DefaultNode),
+    Stream(u32),
+    Level,
+}
+
+impl MailboxKey {
+    fn of(event: &Event) -> Option<Self> {
+        Some(match event {
+            Event::ParamChanged { id, param } => Self::Param {
+                id: *id,
+                param: *param,
+            },
+            Event::Volume(volume) => Self::Volume(volume.kind),
+            Event::StreamVolume(stream) => Self::Stream(stream.node_id),
+            Event::Level(_) => Self::Level,
+            _ => return None,
+        })
+    }
+}
+
+impl Mailbox {
+    /// Returns `false` if the mailbox is closed and the event was dropped.
+    fn push(&self, event: Event) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.closed {
+            return false;
+        }
+        // the replaced event is moved to the back, so it stays after e.g. a `NodeRemoved`
+        if let Some(key) = MailboxKey::of(&event) {
+            queue
+                .events
+                .retain(|x| MailboxKey::of(x).as_ref() != Some(&key));
+        }
+        queue.events.push_back(event);
+        drop(queue);
+        self.notify.notify_one();
+        true
+    }
+    fn close(&self) {
+        self.queue.lock().unwrap().closed = true;
+        self.notify.notify_one();
+    }
+    fn is_closed(&self) -> bool {
+        self.queue.lock().unwrap().closed
+    }
+    fn take(&self) -> (VecDeque<Event>, bool) {
+        let mut queue = self.queue.lock().unwrap();
+        (std::mem::take(&mut queue.events), queue.closed)
+    }
+}
+
+struct CloseOnDrop(Arc<Mailbox>);
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+/// Sends the queued events to the UI until the mailbox is closed and empty.
+async fn deliver<T>(mailbox: Arc<Mailbox>, mut sender: Sender<T>, map: impl Fn(Event) -> T) {
+    loop {
+        mailbox.notify.notified().await;
+        let (events, closed) = mailbox.take();
+        for event in events {
+            if sender.send(map(event)).await.is_err() {
+                mailbox.close();
                 return;
             }
+        }
+        if closed {
+            return;
+        }
+        tokio::time::sleep(DELIVERY_INTERVAL).await;
+    }
+}
+
+/// Everything the listeners share, only lives on the PipeWire thread.
+#[derive(Default)]
+struct State {
+    /// The type of every global we care about, the registry only gives us the id on removal.
+    globals: HashMap<u32, ObjectType>,
+    nodes: HashMap<u32, BoundNode>,
+    devices: HashMap<u32, Device>,
+    default_metadata: Option<BoundMetadata>,
+    meter: Option<Meter>,
+    feedback: Option<Feedback>,
+    last_feedback: Option<Instant>,
+    defaults: HashMap<DefaultNode, DefaultState>,
+    /// `false` until the initial globals and properties are all received.
+    initialized: bool,
+}
+
+#[derive(Default)]
+struct DefaultState {
+    /// The `node.name` of the default node.
+    name: Option<String>,
+    volume: Option<Volume>,
+}
+
+impl State {
+    fn default_node(&self, kind: DefaultNode) -> Option<&BoundNode> {
+        let name = self.defaults.get(&kind)?.name.as_deref()?;
+        self.nodes
+            .values()
+            .find(|node| node.info.name.as_deref() == Some(name))
+    }
+    /// Returns the event to send if the volume of a default node or a stream changed.
+    fn update_props(&mut self, id: u32, props: Props) -> Option<Event> {
+        let node = self.nodes.get_mut(&id)?;
+        if let Some(channel_volumes) = &props.channel_volumes {
+            node.channel_volumes = channel_volumes.clone();
+        }
+        if node.info.is_stream() {
+            let mut stream = node.stream.clone().unwrap_or_else(|| Stream {
+                node_id: id,
+                application_name: node.info.application_name.clone(),
+                icon_name: node.info.application_icon_name.clone(),
+                volume: 0.0,
+                mute: false,
+            });
+            props.apply(&mut stream.volume, &mut stream.mute);
+            if node.stream.as_ref() == Some(&stream) {
+                return None;
+            }
+            node.stream = Some(stream.clone());
+            return Some(Event::StreamVolume(stream));
+        }
+
+        let (kind, description, channels) = DefaultNode::ALL.into_iter().find_map(|kind| {
+            let node = self.default_node(kind).filter(|x| x.info.id == id)?;
+            let channels = node
+                .channel_volumes
+                .iter()
+                .enumerate()
+                .map(|(index, volume)| Channel {
+                    position: node.info.positions.get(index).cloned(),
+                    volume: volume.cbrt(),
+                })
+                .collect::<Vec<_>>();
+            Some((kind, node.info.description.clone(), channels))
+        })?;
+        let default = self.defaults.entry(kind).or_default();
+        let mut volume = default
+            .volume
+            .clone()
+            .filter(|x| x.node_id == id)
+            .unwrap_or(Volume {
+                kind,
+                node_id: id,
+                description,
+                volume: 0.0,
+                mute: false,
+                channels: Vec::new(),
+            });
+        props.apply(&mut volume.volume, &mut volume.mute);
+        volume.channels = channels;
+        if default.volume.as_ref() == Some(&volume) {
+            return None;
+        }
+        default.volume = Some(volume.clone());
+        Some(Event::Volume(volume))
+    }
+    fn switched_node(&self, kind: DefaultNode, name: String) -> SwitchedNode {
+        let node = self.default_node(kind).map(|x| &x.info);
+        let device = node
+            .and_then(|x| x.device_id)
+            .and_then(|x| self.devices.get(&x));
+        SwitchedNode {
+            kind,
+            name,
+            description: node.and_then(|x| x.description.clone()),
+            icon_name: node
+                .and_then(|x| x.icon_name.clone())
+                .or_else(|| device.and_then(|x| x.icon_name.clone())),
+            form_factor: node
+                .and_then(|x| x.form_factor.clone())
+                .or_else(|| device.and_then(|x| x.form_factor.clone())),
+        }
+    }
+    fn run(&mut self, core: &Core, send: &SendEvent, command: Command) -> Result<(), String> {
+        let (node_id, props) = match command {
+            Command::StartMeter => return self.start_meter(core, send),
+            Command::StopMeter => {
+                self.meter = None;
+                return Ok(());
+            }
+            Command::PlayFeedback(sample) => return self.play_feedback(core, sample),
+            Command::SetVolume { node_id, volume } => {
+                let current = self
+                    .nodes
+                    .get(&node_id)
+                    .map(|x| x.channel_volumes.as_slice())
+                    .unwrap_or_default();
+                let channel_volumes = scale_channel_volumes(current, linear_to_cubic(volume));
+                let props = Props {
+                    channel_volumes: Some(channel_volumes),
+                    ..Default::default()
+                };
+                (node_id, props)
+            }
+            Command::SetChannelVolumes { node_id, volumes } => {
+                let props = Props {
+                    channel_volumes: Some(volumes.into_iter().map(linear_to_cubic).collect()),
+                    ..Default::default()
+                };
+                (node_id, props)
+            }
+            Command::SetMute { node_id, mute } => {
+                let props = Props {
+                    mute: Some(mute),
+                    ..Default::default()
+                };
+                (node_id, props)
+            }
+        };
+        let node = self
+            .nodes
+            .get(&node_id)
+            .ok_or_else(|| format!("cannot set props of node {node_id}: no such node"))?;
+        let bytes = props
+            .serialize()
+            .map_err(|e| format!("cannot serialize props {props:?}: {e:?}"))?;
+        let pod = Pod::from_bytes(&bytes)
+            .ok_or_else(|| format!("serialized props {props:?} is not a pod"))?;
+        node.proxy.set_param(ParamType::Props, 0, pod);
+        Ok(())
+    }
+    fn play_feedback(&mut self, core: &Core, sample: Arc<Sample>) -> Result<(), String> {
+        let now = Instant::now();
+        if self
+            .last_feedback
+            .is_some_and(|x| now - x < FEEDBACK_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_feedback = Some(now);
+        self.feedback = None;
+        let feedback =
+            Feedback::play(core, sample).map_err(|e| format!("cannot play the feedback: {e}"))?;
+        self.feedback = Some(feedback);
+        Ok(())
+    }
+    fn start_meter(&mut self, core: &Core, send: &SendEvent) -> Result<(), String> {
+        let Some(target) = self
+            .defaults
+            .get(&DefaultNode::Sink)
+            .and_then(|x| x.name.clone())
+        else {
+            self.meter = None;
+            return Ok(());
         };
-        drop(tx);
+        if self.meter.as_ref().is_some_and(|x| x.target == target) {
+            return Ok(());
+        }
+        // only one capture stream at a time
+        self.meter = None;
+        let meter = Meter::start(core, target.clone(), send.clone())
+            .map_err(|e| format!("cannot start the level meter on {target}: {e}"))?;
+        self.meter = Some(meter);
+        Ok(())
+    }
+}
 
-        let weak_mainloop = mainloop.clone().downgrade();
-        // TODO: find the event to listen
-        let _listener = registry
-            .add_listener_local()
-            .global(move |global| {
-                let mut sender = sender.clone();
-                match &global.type_ {
-                    ObjectType::Metadata => {
-                        let message = format!(
-                            "got a node and the props is {{\n{}}}",
-                            match global.props {
-                                Some(x) => format!(
-                                    "DictRef {{\n{}}}",
-                                    x.iter()
-                                        .map(|(x, y)| format!("\t{}: {}\n", x, y))
-                                        .collect::<String>()
-                                ),
-                                None => "None".to_owned(),
+/// A capture stream on the monitor ports of a sink, it only exists while the UI shows the level.
+struct Meter {
+    /// The `node.name` of the sink.
+    target: String,
+    // the listener has to go before the stream
+    _listener: StreamListener<MeterData>,
+    _stream: pipewire::stream::Stream,
+}
+
+impl Meter {
+    fn start(core: &Core, target: String, send: SendEvent) -> Result<Self, pipewire::Error> {
+        let mut props = Properties::new();
+        props.insert(*keys::MEDIA_TYPE, "Audio");
+        props.insert(*keys::MEDIA_CATEGORY, "Capture");
+        props.insert(*keys::MEDIA_ROLE, "Music");
+        props.insert(*keys::NODE_NAME, METER_NAME);
+        props.insert(*keys::STREAM_CAPTURE_SINK, "true");
+        props.insert(TARGET_OBJECT, target.as_str());
+        let stream = pipewire::stream::Stream::new(core, METER_NAME, props)?;
+        let listener = stream
+            .add_local_listener_with_user_data(MeterData::default())
+            .process(move |stream, data| {
+                let Some(mut buffer) = stream.dequeue_buffer() else {
+                    return;
+                };
+                let Some(buffer_data) = buffer.datas_mut().first_mut() else {
+                    return;
+                };
+                let chunk = buffer_data.chunk();
+                let (offset, size) = (chunk.offset() as usize, chunk.size() as usize);
+                let Some(bytes) = buffer_data.data() else {
+                    return;
+                };
+                data.add(bytes.get(offset..offset + size).unwrap_or_default());
+                if let Some(level) = data.take_level(METER_INTERVAL) {
+                    send(Event::Level(level));
+                }
+            })
+            .register()?;
+
+        // any rate and channel count, the level is the same for every channel
+        let mut audio_info = AudioInfoRaw::new();
+        audio_info.set_format(AudioFormat::F32LE);
+        let bytes = serialize_format(audio_info)?;
+        let format = Pod::from_bytes(&bytes).ok_or(pipewire::Error::CreationFailed)?;
+        stream.connect(
+            Direction::Input,
+            None,
+            StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS,
+            &mut [format],
+        )?;
+        Ok(Self {
+            target,
+            _listener: listener,
+            _stream: stream,
+        })
+    }
+}
+
+/// A playback stream playing a [`Sample`] once on the default sink.
+struct Feedback {
+    // the listener has to go before the stream
+    _listener: StreamListener<usize>,
+    _stream: pipewire::stream::Stream,
+}
+
+impl Feedback {
+    fn play(core: &Core, sample: Arc<Sample>) -> Result<Self, pipewire::Error> {
+        let mut props = Properties::new();
+        props.insert(*keys::MEDIA_TYPE, "Audio");
+        props.insert(*keys::MEDIA_CATEGORY, "Playback");
+        props.insert(*keys::MEDIA_ROLE, "Notification");
+        props.insert(*keys::NODE_NAME, FEEDBACK_NAME);
+        let stream = pipewire::stream::Stream::new(core, FEEDBACK_NAME, props)?;
+        let channels = sample.channels.max(1) as usize;
+        // the user data is the index of the next sample to play
+        let listener = stream
+            .add_local_listener_with_user_data(0)
+            .process({
+                let sample = sample.clone();
+                move |stream, position| {
+                    let Some(mut buffer) = stream.dequeue_buffer() else {
+                        return;
+                    };
+                    let Some(buffer_data) = buffer.datas_mut().first_mut() else {
+                        return;
+                    };
+                    let remaining = sample.samples.get(*position..).unwrap_or_default();
+                    let written = match buffer_data.data() {
+                        Some(bytes) => {
+                            let count = (bytes.len() / size_of::<f32>()).min(remaining.len());
+                            let count = count - count % channels;
+                            for (bytes, x) in bytes
+                                .chunks_exact_mut(size_of::<f32>())
+                                .zip(&remaining[..count])
+                            {
+                                bytes.copy_from_slice(&x.to_le_bytes());
                             }
-                        );
+                            count
+                        }
+                        None => 0,
+                    };
+                    *position += written;
+                    let chunk = buffer_data.chunk_mut();
+                    *chunk.offset_mut() = 0;
+                    *chunk.stride_mut() = (channels * size_of::<f32>()) as i32;
+                    *chunk.size_mut() = (written * size_of::<f32>()) as u32;
+                    if *position >= sample.samples.len() {
+                        drop(buffer);
+                        let _ = stream.flush(true);
+                    }
+                }
+            })
+            // done playing, the stream is dropped with the next feedback
+            .drained(|stream, _| {
+                let _ = stream.set_active(false);
+            })
+            .register()?;
+
+        let mut audio_info = AudioInfoRaw::new();
+        audio_info.set_format(AudioFormat::F32LE);
+        audio_info.set_rate(sample.rate);
+        audio_info.set_channels(sample.channels.max(1));
+        let bytes = serialize_format(audio_info)?;
+        let format = Pod::from_bytes(&bytes).ok_or(pipewire::Error::CreationFailed)?;
+        stream.connect(
+            Direction::Output,
+            None,
+            StreamFlags::AUTOCONNECT | StreamFlags::MAP_BUFFERS,
+            &mut [format],
+        )?;
+        Ok(Self {
+            _listener: listener,
+            _stream: stream,
+        })
+    }
+}
+
+/// The `EnumFormat` param of our streams.
+fn serialize_format(audio_info: AudioInfoRaw) -> Result<Vec<u8>, pipewire::Error> {
+    let format = Value::Object(Object {
+        type_: SpaTypes::ObjectParamFormat.as_raw(),
+        id: ParamType::EnumFormat.as_raw(),
+        properties: audio_info.into(),
+    });
+    let (bytes, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &format)
+        .map_err(|_| pipewire::Error::CreationFailed)?;
+    Ok(bytes.into_inner())
+}
+
+/// The samples captured since the last [`Event::Level`].
+#[derive(Default)]
+struct MeterData {
+    peak: f32,
+    square_sum: f32,
+    count: usize,
+    last_sent: Option<Instant>,
+}
+
+impl MeterData {
+    /// `bytes` are interleaved `F32LE` samples.
+    fn add(&mut self, bytes: &[u8]) {
+        for sample in bytes.chunks_exact(size_of::<f32>()) {
+            let sample = f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]);
+            self.peak = self.peak.max(sample.abs());
+            self.square_sum += sample * sample;
+            self.count += 1;
+        }
+    }
+    /// Returns the level and starts over, at most once per `interval`.
+    fn take_level(&mut self, interval: Duration) -> Option<Level> {
+        let now = Instant::now();
+        if self.count == 0 || self.last_sent.is_some_and(|x| now - x < interval) {
+            return None;
+        }
+        let level = Level {
+            peak: self.peak,
+            rms: (self.square_sum / self.count as f32).sqrt(),
+        };
+        *self = Self {
+            last_sent: Some(now),
+            ..Default::default()
+        };
+        Some(level)
+    }
+}
+
+/// A node proxy and its listener, both have to be kept alive to receive the param events.
+struct BoundNode {
+    info: Node,
+    /// `channelVolumes` of the last `Props` param, in PipeWire's cubic scale.
+    channel_volumes: Vec<f32>,
+    /// The last volume we sent, only for streams.
+    stream: Option<Stream>,
+    proxy: node::Node,
+    _listener: NodeListener,
+}
+
+impl BoundNode {
+    fn bind(
+        registry: &Registry,
+        global: &GlobalObject<&DictRef>,
+        info: Node,
+        state: Weak<RefCell<State>>,
+        send: SendEvent,
+    ) -> Result<Self, pipewire::Error> {
+        let proxy: node::Node = registry.bind(global)?;
+        let id = global.id;
+        let listener = proxy
+            .add_listener_local()
+            .param(move |_seq, param, _index, _next, pod| {
+                send(Event::ParamChanged { id, param });
+                if param != ParamType::Props {
+                    return;
+                }
+                let Some(props) = pod.and_then(Props::parse) else {
+                    return;
+                };
+                let Some(state) = state.upgrade() else {
+                    return;
+                };
+                let event = state.borrow_mut().update_props(id, props);
+                if let Some(event) = event {
+                    send(event);
+                }
+            })
+            .register();
+        proxy.subscribe_params(SUBSCRIBED_PARAMS);
+        Ok(Self {
+            info,
+            channel_volumes: Vec::new(),
+            stream: None,
+            proxy,
+            _listener: listener,
+        })
+    }
+}
+
+/// The `default` metadata, it holds the names of the default sink and source.
+struct BoundMetadata {
+    id: u32,
+    _proxy: metadata::Metadata,
+    _listener: MetadataListener,
+}
 
-                        if executor::block_on(sender.send(map(message))).is_err() {
-                            if let Some(mainloop) = weak_mainloop.upgrade() {
-                                mainloop.quit();
+impl BoundMetadata {
+    fn bind(
+        registry: &Registry,
+        global: &GlobalObject<&DictRef>,
+        state: Weak<RefCell<State>>,
+        send: SendEvent,
+    ) -> Result<Self, pipewire::Error> {
+        let proxy: metadata::Metadata = registry.bind(global)?;
+        let listener = proxy
+            .add_listener_local()
+            .property(move |_subject, key, _type, value| {
+                // `None` key means every property is removed
+                let kinds = DefaultNode::ALL
+                    .into_iter()
+                    .filter(|kind| key.is_none_or(|x| x == kind.metadata_key()));
+                let Some(state) = state.upgrade() else {
+                    return 0;
+                };
+                for kind in kinds {
+                    let name = value.and_then(|value| {
+                        match serde_json::from_str::<MetadataValue>(value) {
+                            Ok(x) => Some(x.name),
+                            Err(e) => {
+                                send(Event::Error(format!(
+                                    "cannot parse {} ({value}): {e}",
+                                    kind.metadata_key()
+                                )));
+                                None
                             }
                         }
+                    });
+                    let mut state = state.borrow_mut();
+                    let default = state.defaults.entry(kind).or_default();
+                    if default.name == name {
+                        continue;
+                    }
+                    default.name = name.clone();
+                    default.volume = None;
+                    // the params are only sent on change, ask for the current volume of the new node
+                    if let Some(node) = state.default_node(kind) {
+                        node.proxy
+                            .enum_params(0, Some(ParamType::Props), 0, u32::MAX);
+                    }
+                    let switched = name
+                        .clone()
+                        .filter(|_| state.initialized)
+                        .map(|name| state.switched_node(kind, name));
+                    drop(state);
+                    send(Event::DefaultNodeChanged { kind, name });
+                    if let Some(switched) = switched {
+                        send(Event::DefaultNodeSwitched(switched));
                     }
-                    _ => (),
-                    //other => tx
-                    //    .send(format!("got: `{}` but i dont care", other))
-                    //    .unwrap(),
                 }
+                0
             })
             .register();
-        mainloop.run()
-    });
+        Ok(Self {
+            id: global.id,
+            _proxy: proxy,
+            _listener: listener,
+        })
+    }
+}
+
+/// Values of the `default` metadata look like `{ "name": "alsa_output.pci-0000_00_1f.3" }`.
+#[derive(Deserialize)]
+struct MetadataValue {
+    name: String,
+}
 
-    match rx.await {
-        Ok(e) => {
-            handle.await.unwrap();
-            Err(e)
+/// The part of the `Props` param we care about.
+#[derive(Debug, Default)]
+struct Props {
+    channel_volumes: Option<Vec<f32>>,
+    mute: Option<bool>,
+}
+
+impl Props {
+    fn parse(pod: &Pod) -> Option<Self> {
+        let (_, Value::Object(Object { properties, .. })) =
+            PodDeserializer::deserialize_any_from(pod.as_bytes()).ok()?
+        else {
+            return None;
+        };
+        let mut props = Self::default();
+        for property in properties {
+            match (property.key, property.value) {
+                (spa::sys::SPA_PROP_channelVolumes, Value::ValueArray(ValueArray::Float(x))) => {
+                    props.channel_volumes = Some(x)
+                }
+                (spa::sys::SPA_PROP_mute, Value::Bool(x)) => props.mute = Some(x),
+                _ => (),
+            }
         }
-        Err(_) => Ok(handle)
+        Some(props)
+    }
+    fn apply(&self, volume: &mut f32, mute: &mut bool) {
+        if let Some(channel_volumes) = &self.channel_volumes {
+            *volume = cubic_to_linear(channel_volumes);
+        }
+        if let Some(x) = self.mute {
+            *mute = x;
+        }
+    }
+    fn serialize(&self) -> Result<Vec<u8>, GenError> {
+        let mut properties = Vec::new();
+        if let Some(x) = &self.channel_volumes {
+            properties.push(Property::new(
+                spa::sys::SPA_PROP_channelVolumes,
+                Value::ValueArray(ValueArray::Float(x.clone())),
+            ));
+        }
+        if let Some(x) = self.mute {
+            properties.push(Property::new(spa::sys::SPA_PROP_mute, Value::Bool(x)));
+        }
+        let object = Value::Object(Object {
+            type_: spa::sys::SPA_TYPE_OBJECT_Props,
+            id: spa::sys::SPA_PARAM_Props,
+            properties,
+        });
+        let (bytes, _) = PodSerializer::serialize(Cursor::new(Vec::new()), &object)?;
+        Ok(bytes.into_inner())
+    }
+}
+
+/// PipeWire stores the volume cubed, `wpctl` shows the cube root of it.
+fn cubic_to_linear(channel_volumes: &[f32]) -> f32 {
+    if channel_volumes.is_empty() {
+        return 0.0;
+    }
+    let average = channel_volumes.iter().sum::<f32>() / channel_volumes.len() as f32;
+    average.cbrt()
+}
+
+fn linear_to_cubic(volume: f32) -> f32 {
+    volume.max(0.0).powi(3)
+}
+
+/// Scales the channels so their average is `average`, keeping the balance between them.
+///
+/// All the channels get `average` if they are all silent, one channel if we never got the volume.
+fn scale_channel_volumes(current: &[f32], average: f32) -> Vec<f32> {
+    let current_average = current.iter().sum::<f32>() / current.len().max(1) as f32;
+    if current_average <= 0.0 {
+        return vec![average; current.len().max(1)];
+    }
+    current
+        .iter()
+        .map(|x| x * average / current_average)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{
+            Monitor,
+            test_util::{TIMEOUT, next, spawn_or_skip},
+        },
+        *,
+    };
+    use cosmic::iced::futures::channel::mpsc::channel;
+    use std::{
+        fs,
+        path::PathBuf,
+        process::{self, Child, Stdio},
+    };
+
+    /// A null sink as the only sink, WirePlumber makes it the default and links the meter to it.
+    const CONFIG: &str = r#"
+context.properties = {
+    core.daemon = true
+    core.name = pipewire-0
+    support.dbus = false
+}
+context.spa-libs = {
+    audio.convert.* = audioconvert/libspa-audioconvert
+    support.* = support/libspa-support
+}
+context.modules = [
+    { name = libpipewire-module-protocol-native }
+    { name = libpipewire-module-metadata }
+    { name = libpipewire-module-spa-node-factory }
+    { name = libpipewire-module-client-node }
+    { name = libpipewire-module-adapter }
+    { name = libpipewire-module-link-factory }
+    { name = libpipewire-module-session-manager flags = [ ifexists nofail ] }
+]
+context.objects = [
+    { factory = spa-node-factory
+        args = {
+            factory.name = support.node.driver
+            node.name = Dummy-Driver
+            priority.driver = 20000
+        }
+    }
+    { factory = adapter
+        args = {
+            factory.name = support.null-audio-sink
+            node.name = snacks-test-sink
+            media.class = Audio/Sink
+            audio.position = [ FL FR ]
+            object.linger = true
+        }
+    }
+]
+"#;
+
+    const TONE_AMPLITUDE: f32 = 0.5;
+
+    /// Five seconds of a 440 Hz sine wave in stereo, far longer than the test needs.
+    fn tone(amplitude: f32) -> Sample {
+        const RATE: u32 = 48000;
+        let samples = (0..RATE * 5)
+            .flat_map(|i| {
+                let x =
+                    amplitude * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / RATE as f32).sin();
+                [x, x]
+            })
+            .collect();
+        Sample {
+            rate: RATE,
+            channels: 2,
+            samples,
+        }
+    }
+
+    /// A headless PipeWire with WirePlumber in a temporary runtime directory, killed on drop.
+    struct Daemon {
+        runtime_dir: tempfile::TempDir,
+        children: Vec<Child>,
+    }
+
+    impl Daemon {
+        /// `None` if PipeWire or WirePlumber isn't installed.
+        fn spawn() -> Option<Self> {
+            let mut daemon = Self {
+                runtime_dir: tempfile::tempdir().unwrap(),
+                children: Vec::new(),
+            };
+            let config = daemon.runtime_dir.path().join("pipewire.conf");
+            fs::write(&config, CONFIG).unwrap();
+            let child = spawn_or_skip(daemon.command("pipewire").arg("-c").arg(&config))?;
+            daemon.children.push(child);
+            let start = Instant::now();
+            while !daemon.socket().exists() {
+                assert!(
+                    start.elapsed() < TIMEOUT,
+                    "pipewire didn't create its socket"
+                );
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            let child = spawn_or_skip(&mut daemon.command("wireplumber"))?;
+            daemon.children.push(child);
+            Some(daemon)
+        }
+        fn command(&self, program: &str) -> process::Command {
+            let dir = self.runtime_dir.path();
+            let mut command = process::Command::new(program);
+            command
+                .env("PIPEWIRE_RUNTIME_DIR", dir)
+                .env("XDG_RUNTIME_DIR", dir)
+                // WirePlumber remembers the default nodes there
+                .env("XDG_STATE_HOME", dir)
+                .env_remove("DBUS_SESSION_BUS_ADDRESS")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            command
+        }
+        fn socket(&self) -> PathBuf {
+            self.runtime_dir.path().join("pipewire-0")
+        }
+    }
+
+    impl Drop for Daemon {
+        fn drop(&mut self) {
+            for child in self.children.iter_mut().rev() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn meter_sends_levels_of_the_default_sink() {
+        let Some(daemon) = Daemon::spawn() else {
+            return;
+        };
+        let (sender, mut receiver) = channel(100);
+        let remote = daemon.socket().to_str().unwrap().to_owned();
+        let monitor = PipeWireMonitor {
+            remote: Some(remote),
+            ..Default::default()
+        };
+        let handle = monitor.start(sender, |x| x).await.unwrap();
+
+        let mut controller = None;
+        // the meter captures the default sink, so it has to be known first
+        let sink = loop {
+            match next(&mut receiver).await {
+                Event::Ready(x) => controller = Some(x),
+                Event::Volume(volume) if volume.kind == DefaultNode::Sink => break volume,
+                Event::Error(e) => panic!("{e}"),
+                _ => (),
+            }
+        };
+        let controller = controller.expect("the volume came before the controller");
+        // WirePlumber gives new sinks a lower volume, the tone has to reach the meter unchanged
+        if sink.volume != 1.0 || sink.mute {
+            controller.send(Command::SetVolume {
+                node_id: sink.node_id,
+                volume: 1.0,
+            });
+            controller.send(Command::SetMute {
+                node_id: sink.node_id,
+                mute: false,
+            });
+            loop {
+                match next(&mut receiver).await {
+                    Event::Volume(x) if x.node_id == sink.node_id => {
+                        if (x.volume - 1.0).abs() < 0.001 && !x.mute {
+                            break;
+                        }
+                    }
+                    Event::Error(e) => panic!("{e}"),
+                    _ => (),
+                }
+            }
+        }
+
+        controller.send(Command::StartMeter);
+        controller.send(Command::PlayFeedback(Arc::new(tone(TONE_AMPLITUDE))));
+        // the first level with the tone may be half silence, the one after it is only the tone
+        let mut heard = false;
+        let level = loop {
+            match next(&mut receiver).await {
+                Event::Level(level) if heard => break level,
+                Event::Level(level) => heard = level.peak > 0.0,
+                Event::Error(e) => panic!("{e}"),
+                _ => (),
+            }
+        };
+        // a sine wave's RMS is its amplitude over the square root of 2
+        let rms = TONE_AMPLITUDE / 2.0_f32.sqrt();
+        assert!((level.peak - TONE_AMPLITUDE).abs() < 0.05, "{level:?}");
+        assert!((level.rms - rms).abs() < 0.05, "{level:?}");
+
+        controller.shutdown();
+        handle.await.unwrap();
     }
 }
@@ -0,0 +1,390 @@
+use super::subscribe;
+use cosmic::{
+    iced::futures::SinkExt,
+    iced_futures::futures::{TryStreamExt, channel::mpsc::Sender},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use update::{Update, macros::Update};
+use zbus::{
+    Connection, MatchRule, Proxy,
+    zvariant::{DeserializeDict, SerializeDict, Type},
+};
+
+const UPOWER: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+const DISPLAY_DEVICE_PATH: &str = "/org/freedesktop/UPower/devices/DisplayDevice";
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    PluggedIn(Status),
+    Unplugged(Status),
+    /// The battery went under one of the [`Thresholds`] while discharging.
+    Low(Status),
+    Critical(Status),
+    Error(String),
+}
+
+/// Battery levels in percent, from `0.0` to `100.0`.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Thresholds {
+    pub low: f64,
+    pub critical: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            low: 20.0,
+            critical: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+    pub on_battery: bool,
+    /// `None` without a battery.
+    pub percentage: Option<f64>,
+    pub state: BatteryState,
+    /// `None` if UPower doesn't know yet.
+    pub time_to_empty: Option<Duration>,
+}
+
+/// UPower's `State` of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum BatteryState {
+    #[default]
+    Unknown,
+    Charging,
+    Discharging,
+    Empty,
+    FullyCharged,
+    PendingCharge,
+    PendingDischarge,
+}
+
+impl From<u32> for BatteryState {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => Self::Charging,
+            2 => Self::Discharging,
+            3 => Self::Empty,
+            4 => Self::FullyCharged,
+            5 => Self::PendingCharge,
+            6 => Self::PendingDischarge,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// The part of `org.freedesktop.UPower.Device` we care about.
+#[derive(SerializeDict, DeserializeDict, Type, Clone, Debug, Default, Update)]
+#[zvariant(signature = "a{sv}", rename_all = "PascalCase")]
+struct DeviceProperties {
+    is_present: Option<bool>,
+    percentage: Option<f64>,
+    state: Option<u32>,
+    time_to_empty: Option<i64>,
+}
+
+/// The part of `org.freedesktop.UPower` we care about.
+#[derive(SerializeDict, DeserializeDict, Type, Clone, Debug, Default, Update)]
+#[zvariant(signature = "a{sv}", rename_all = "PascalCase")]
+struct UPowerProperties {
+    on_battery: Option<bool>,
+}
+
+/// How low the battery is, ordered from the best to the worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Normal,
+    Low,
+    Critical,
+}
+
+/// Follows UPower from a [`super::Registry`], warning at `thresholds`.
+pub struct PowerMonitor {
+    pub thresholds: Thresholds,
+}
+
+impl super::Monitor for PowerMonitor {
+    type Event = Event;
+    type Error = zbus::Error;
+
+    fn name(&self) -> &'static str {
+        "power"
+    }
+    async fn start<T: Send + 'static>(
+        &self,
+        sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Result<JoinHandle<()>, zbus::Error> {
+        start_with_connection(sender, map, self.thresholds, Connection::system().await?).await
+    }
+}
+
+/// Follows UPower's display device, the combination of all the batteries, and `OnBattery`, on any
+/// bus with an `org.freedesktop.UPower`.
+async fn start_with_connection<T: Send + 'static>(
+    mut sender: Sender<T>,
+    map: impl Fn(Event) -> T + Send + Sync + 'static,
+    thresholds: Thresholds,
+    connection: Connection,
+) -> Result<JoinHandle<()>, zbus::Error> {
+    let rule = MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .sender(UPOWER)?
+        .interface(PROPERTIES_INTERFACE)?
+        .member("PropertiesChanged")?
+        .build();
+    let mut stream = subscribe(&connection, rule).await?;
+    let mut upower: UPowerProperties = get_all(&connection, UPOWER_PATH, UPOWER).await?;
+    let mut device: DeviceProperties =
+        get_all(&connection, DISPLAY_DEVICE_PATH, DEVICE_INTERFACE).await?;
+    let mut level = battery_level(&device, &thresholds);
+
+    Ok(tokio::spawn(async move {
+        let mut send = async |event| {
+            if let Err(e) = sender.send(map(event)).await {
+                tracing::error!("Cannot send to sender: {e}");
+            }
+        };
+        loop {
+            let message = match stream.try_next().await {
+                Ok(Some(x)) => x,
+                Ok(None) => {
+                    tracing::info!("message stream ended");
+                    break;
+                }
+                Err(e) => {
+                    tracing::error!("error: {e}");
+                    break;
+                }
+            };
+            let path = message.header().path().map(|x| x.to_string());
+            let body = message.body();
+            let event = match path.as_deref() {
+                Some(UPOWER_PATH) => {
+                    match body.deserialize::<(String, UPowerProperties, Vec<String>)>() {
+                        Ok((_, changed, invalidated)) => {
+                            let was_on_battery = upower.on_battery;
+                            upower.update(changed);
+                            upower.remove(invalidated.as_slice());
+                            match (was_on_battery, upower.on_battery) {
+                                (Some(true), Some(false)) => {
+                                    level = Level::Normal;
+                                    Some(Event::PluggedIn(status(&upower, &device)))
+                                }
+                                (Some(false), Some(true)) => {
+                                    Some(Event::Unplugged(status(&upower, &device)))
+                                }
+                                _ => None,
+                            }
+                        }
+                        Err(e) => Some(Event::Error(format!("deserialize error: {e}"))),
+                    }
+                }
+                Some(DISPLAY_DEVICE_PATH) => {
+                    match body.deserialize::<(String, DeviceProperties, Vec<String>)>() {
+                        Ok((_, changed, invalidated)) => {
+                            device.update(changed);
+                            device.remove(invalidated.as_slice());
+                            let new_level = battery_level(&device, &thresholds);
+                            let discharging = BatteryState::from(device.state.unwrap_or_default())
+                                == BatteryState::Discharging;
+                            let worse = new_level > level;
+                            level = new_level;
+                            match new_level {
+                                Level::Low if worse && discharging => {
+                                    Some(Event::Low(status(&upower, &device)))
+                                }
+                                Level::Critical if worse && discharging => {
+                                    Some(Event::Critical(status(&upower, &device)))
+                                }
+                                _ => None,
+                            }
+                        }
+                        Err(e) => Some(Event::Error(format!("deserialize error: {e}"))),
+                    }
+                }
+                _ => None,
+            };
+            if let Some(event) = event {
+                send(event).await;
+            }
+        }
+    }))
+}
+
+async fn get_all<T: DeserializeOwned + Type>(
+    connection: &Connection,
+    path: &str,
+    interface: &str,
+) -> Result<T, zbus::Error> {
+    let proxy = Proxy::new(connection, UPOWER, path, PROPERTIES_INTERFACE).await?;
+    proxy.call("GetAll", &(interface,)).await
+}
+
+fn battery_level(device: &DeviceProperties, thresholds: &Thresholds) -> Level {
+    match device.percentage {
+        _ if device.is_present == Some(false) => Level::Normal,
+        Some(x) if x <= thresholds.critical => Level::Critical,
+        Some(x) if x <= thresholds.low => Level::Low,
+        _ => Level::Normal,
+    }
+}
+
+fn status(upower: &UPowerProperties, device: &DeviceProperties) -> Status {
+    Status {
+        on_battery: upower.on_battery.unwrap_or_default(),
+        percentage: device
+            .percentage
+            .filter(|_| device.is_present != Some(false)),
+        state: device.state.unwrap_or_default().into(),
+        time_to_empty: device
+            .time_to_empty
+            .filter(|x| *x > 0)
+            .map(|x| Duration::from_secs(x as u64)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::test_util::{PrivateBus, next},
+        *,
+    };
+    use cosmic::iced::futures::channel::mpsc::channel;
+
+    struct UPower {
+        on_battery: bool,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.UPower")]
+    impl UPower {
+        #[zbus(property)]
+        fn on_battery(&self) -> bool {
+            self.on_battery
+        }
+    }
+
+    struct DisplayDevice {
+        percentage: f64,
+        state: u32,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.UPower.Device")]
+    impl DisplayDevice {
+        #[zbus(property)]
+        fn is_present(&self) -> bool {
+            true
+        }
+        #[zbus(property)]
+        fn percentage(&self) -> f64 {
+            self.percentage
+        }
+        #[zbus(property)]
+        fn state(&self) -> u32 {
+            self.state
+        }
+        #[zbus(property)]
+        fn time_to_empty(&self) -> i64 {
+            3600
+        }
+    }
+
+    async fn set_on_battery(upower: &Connection, on_battery: bool) {
+        let stub = upower
+            .object_server()
+            .interface::<_, UPower>(UPOWER_PATH)
+            .await
+            .unwrap();
+        stub.get_mut().await.on_battery = on_battery;
+        let emitter = stub.signal_emitter();
+        stub.get().await.on_battery_changed(emitter).await.unwrap();
+    }
+
+    async fn set_battery(upower: &Connection, state: BatteryState, percentage: f64) {
+        let stub = upower
+            .object_server()
+            .interface::<_, DisplayDevice>(DISPLAY_DEVICE_PATH)
+            .await
+            .unwrap();
+        let state = match state {
+            BatteryState::Charging => 1,
+            BatteryState::Discharging => 2,
+            _ => 0,
+        };
+        let emitter = stub.signal_emitter();
+        if std::mem::replace(&mut stub.get_mut().await.state, state) != state {
+            stub.get().await.state_changed(emitter).await.unwrap();
+        }
+        stub.get_mut().await.percentage = percentage;
+        stub.get().await.percentage_changed(emitter).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn plug_and_thresholds() {
+        let Some(bus) = PrivateBus::spawn() else {
+            return;
+        };
+        let upower = bus
+            .serve(UPOWER, UPOWER_PATH, UPower { on_battery: false })
+            .await;
+        let device = DisplayDevice {
+            percentage: 50.0,
+            state: 1,
+        };
+        upower
+            .object_server()
+            .at(DISPLAY_DEVICE_PATH, device)
+            .await
+            .unwrap();
+        let (sender, mut receiver) = channel(10);
+        let thresholds = Thresholds {
+            low: 20.0,
+            critical: 5.0,
+        };
+        let handle = start_with_connection(sender, |x| x, thresholds, bus.connect().await)
+            .await
+            .unwrap();
+
+        set_on_battery(&upower, true).await;
+        let Event::Unplugged(status) = next(&mut receiver).await else {
+            panic!("not unplugged");
+        };
+        assert!(status.on_battery);
+        assert_eq!(status.percentage, Some(50.0));
+        assert_eq!(status.time_to_empty, Some(Duration::from_secs(3600)));
+
+        // the low warning comes once, the next event is the critical one
+        set_battery(&upower, BatteryState::Discharging, 20.0).await;
+        let Event::Low(status) = next(&mut receiver).await else {
+            panic!("not low");
+        };
+        assert_eq!(status.percentage, Some(20.0));
+        assert_eq!(status.state, BatteryState::Discharging);
+        set_battery(&upower, BatteryState::Discharging, 12.0).await;
+        set_battery(&upower, BatteryState::Discharging, 5.0).await;
+        let Event::Critical(status) = next(&mut receiver).await else {
+            panic!("not critical");
+        };
+        assert_eq!(status.percentage, Some(5.0));
+
+        // charging under a threshold isn't a warning, so the next event is the plug
+        set_battery(&upower, BatteryState::Charging, 50.0).await;
+        set_battery(&upower, BatteryState::Charging, 15.0).await;
+        set_on_battery(&upower, false).await;
+        let Event::PluggedIn(status) = next(&mut receiver).await else {
+            panic!("not plugged in");
+        };
+        assert!(!status.on_battery);
+        assert_eq!(status.state, BatteryState::Charging);
+        handle.abort();
+    }
+}
@@ -0,0 +1,322 @@
+use cosmic::{
+    iced::futures::SinkExt,
+    iced_futures::futures::{TryStreamExt, channel::mpsc::Sender},
+};
+use std::collections::HashMap;
+use tokio::task::JoinHandle;
+use update::{Update, macros::Update};
+use zbus::{
+    Connection, MatchRule, Proxy,
+    zvariant::{DeserializeDict, OwnedValue, SerializeDict, Type},
+};
+
+/// The newer name comes first, power-profiles-daemon still owns both.
+const SERVICES: [Service; 2] = [
+    Service {
+        name: "org.freedesktop.UPower.PowerProfiles",
+        path: "/org/freedesktop/UPower/PowerProfiles",
+    },
+    Service {
+        name: "net.hadess.PowerProfiles",
+        path: "/net/hadess/PowerProfiles",
+    },
+];
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Sent once on start, the controller changes the profile.
+    Ready(Controller),
+    /// The new `ActiveProfile`, e.g. "power-saver", not sent for the profile found on start.
+    Changed(String),
+    Error(String),
+}
+
+/// The bus name and the object path, the interface has the same name as the service.
+#[derive(Debug, Clone, Copy)]
+struct Service {
+    name: &'static str,
+    path: &'static str,
+}
+
+#[derive(SerializeDict, DeserializeDict, Type, Clone, Debug, Default, Update)]
+#[zvariant(signature = "a{sv}", rename_all = "PascalCase")]
+struct Properties {
+    active_profile: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Controller {
+    connection: Connection,
+    service: Service,
+}
+
+impl Controller {
+    /// Switches to the profile after the active one, in the order the daemon lists them, returns
+    /// the new profile.
+    pub async fn cycle(&self) -> zbus::Result<String> {
+        let proxy = Proxy::new(
+            &self.connection,
+            self.service.name,
+            self.service.path,
+            self.service.name,
+        )
+        .await?;
+        let active: String = proxy.get_property("ActiveProfile").await?;
+        let profiles: Vec<HashMap<String, OwnedValue>> = proxy.get_property("Profiles").await?;
+        let profiles = profiles
+            .iter()
+            .filter_map(|x| String::try_from(x.get("Profile")?.try_clone().ok()?).ok())
+            .collect::<Vec<_>>();
+        let next = profiles
+            .iter()
+            .position(|x| *x == active)
+            .map_or(0, |x| (x + 1) % profiles.len());
+        let Some(next) = profiles.get(next) else {
+            return Ok(active);
+        };
+        proxy.set_property("ActiveProfile", next.as_str()).await?;
+        Ok(next.clone())
+    }
+}
+
+/// The name shown to the user, e.g. "Power saver" for "power-saver".
+pub fn display_name(profile: &str) -> String {
+    let mut name = profile.replace('-', " ");
+    if let Some(first) = name.get_mut(0..1) {
+        first.make_ascii_uppercase();
+    }
+    name
+}
+
+/// Follows power-profiles-daemon from a [`super::Registry`].
+pub struct PowerProfilesMonitor;
+
+impl super::Monitor for PowerProfilesMonitor {
+    type Event = Event;
+    type Error = zbus::Error;
+
+    fn name(&self) -> &'static str {
+        "power_profiles"
+    }
+    async fn start<T: Send + 'static>(
+        &self,
+        sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Result<JoinHandle<()>, zbus::Error> {
+        start_with_connection(sender, map, Connection::system().await?).await
+    }
+}
+
+/// Follows the `ActiveProfile` of power-profiles-daemon on any bus, e.g. a private bus with a
+/// stub in tests.
+async fn start_with_connection<T: Send + 'static>(
+    mut sender: Sender<T>,
+    map: impl Fn(Event) -> T + Send + Sync + 'static,
+    connection: Connection,
+) -> Result<JoinHandle<()>, zbus::Error> {
+    let mut found = None;
+    for service in SERVICES {
+        let rule = MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .sender(service.name)?
+            .path(service.path)?
+            .interface(PROPERTIES_INTERFACE)?
+            .member("PropertiesChanged")?
+            .build();
+        let stream = super::subscribe(&connection, rule).await?;
+        let proxy = Proxy::new(&connection, service.name, service.path, service.name).await?;
+        match proxy.get_property::<String>("ActiveProfile").await {
+            Ok(profile) => {
+                found = Some((service, profile, stream));
+                break;
+            }
+            Err(e) => tracing::info!("no power profiles at {}: {e}", service.name),
+        }
+    }
+    let Some((service, profile, mut stream)) = found else {
+        return Err(zbus::Error::Failure(
+            "power-profiles-daemon is not running".to_owned(),
+        ));
+    };
+    let controller = Controller {
+        connection,
+        service,
+    };
+
+    Ok(tokio::spawn(async move {
+        let mut send = async |event| {
+            if let Err(e) = sender.send(map(event)).await {
+                tracing::error!("Cannot send to sender: {e}");
+            }
+        };
+        send(Event::Ready(controller)).await;
+        let mut properties = Properties {
+            active_profile: Some(profile),
+        };
+        loop {
+            match stream.try_next().await {
+                Ok(Some(message)) => {
+                    match message
+                        .body()
+                        .deserialize::<(String, Properties, Vec<String>)>()
+                    {
+                        Ok((interface, changed, invalidated)) if interface == service.name => {
+                            let previous = properties.active_profile.clone();
+                            properties.update(changed);
+                            properties.remove(invalidated.as_slice());
+                            let profile = properties.active_profile.clone();
+                            let new_profile = profile.filter(|x| previous.as_ref() != Some(x));
+                            if let Some(profile) = new_profile {
+                                send(Event::Changed(profile)).await;
+                            }
+                        }
+                        Ok(_) => (),
+                        Err(e) => {
+                            send(Event::Error(format!("deserialize error: {e}"))).await;
+                        }
+                    }
+                }
+                Ok(None) => {
+                    tracing::info!("message stream ended");
+                    break;
+                }
+                Err(e) => {
+                    tracing::error!("error: {e}");
+                    break;
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::test_util::{PrivateBus, next},
+        *,
+    };
+    use cosmic::iced::futures::channel::mpsc::{Receiver, channel};
+    use zbus::zvariant::Value;
+
+    /// What both names of the stub daemon share.
+    struct Daemon {
+        active_profile: String,
+    }
+
+    impl Daemon {
+        fn profiles() -> Vec<HashMap<String, OwnedValue>> {
+            ["power-saver", "balanced", "performance"]
+                .into_iter()
+                .map(|x| {
+                    let profile = Value::from(x).try_into().unwrap();
+                    HashMap::from([("Profile".to_owned(), profile)])
+                })
+                .collect()
+        }
+    }
+
+    struct UPowerProfiles(Daemon);
+
+    #[zbus::interface(name = "org.freedesktop.UPower.PowerProfiles")]
+    impl UPowerProfiles {
+        #[zbus(property)]
+        fn active_profile(&self) -> String {
+            self.0.active_profile.clone()
+        }
+        #[zbus(property)]
+        fn set_active_profile(&mut self, profile: String) {
+            self.0.active_profile = profile;
+        }
+        #[zbus(property)]
+        fn profiles(&self) -> Vec<HashMap<String, OwnedValue>> {
+            Daemon::profiles()
+        }
+    }
+
+    /// The older name, alone on the bus like with an older daemon.
+    struct HadessProfiles(Daemon);
+
+    #[zbus::interface(name = "net.hadess.PowerProfiles")]
+    impl HadessProfiles {
+        #[zbus(property)]
+        fn active_profile(&self) -> String {
+            self.0.active_profile.clone()
+        }
+        #[zbus(property)]
+        fn set_active_profile(&mut self, profile: String) {
+            self.0.active_profile = profile;
+        }
+        #[zbus(property)]
+        fn profiles(&self) -> Vec<HashMap<String, OwnedValue>> {
+            Daemon::profiles()
+        }
+    }
+
+    async fn serve(bus: &PrivateBus, service: Service, active_profile: &str) -> Connection {
+        let daemon = Daemon {
+            active_profile: active_profile.to_owned(),
+        };
+        match service.name {
+            "net.hadess.PowerProfiles" => {
+                bus.serve(service.name, service.path, HadessProfiles(daemon))
+                    .await
+            }
+            _ => {
+                bus.serve(service.name, service.path, UPowerProfiles(daemon))
+                    .await
+            }
+        }
+    }
+
+    async fn next_profile(receiver: &mut Receiver<Event>) -> String {
+        match next(receiver).await {
+            Event::Changed(x) => x,
+            Event::Ready(_) => panic!("ready again"),
+            Event::Error(e) => panic!("{e}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn cycle_on_both_names() {
+        for service in SERVICES {
+            // a bus each, the other name is not around
+            let Some(bus) = PrivateBus::spawn() else {
+                return;
+            };
+            let _daemon = serve(&bus, service, "performance").await;
+            let (sender, mut receiver) = channel(10);
+            let handle = start_with_connection(sender, |x| x, bus.connect().await)
+                .await
+                .unwrap();
+            let Event::Ready(controller) = next(&mut receiver).await else {
+                panic!("not ready");
+            };
+            assert_eq!(controller.service.name, service.name);
+
+            // the last profile cycles back to the first one
+            assert_eq!(controller.cycle().await.unwrap(), "power-saver");
+            assert_eq!(next_profile(&mut receiver).await, "power-saver");
+            assert_eq!(controller.cycle().await.unwrap(), "balanced");
+            assert_eq!(next_profile(&mut receiver).await, "balanced");
+            handle.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn without_the_daemon() {
+        let Some(bus) = PrivateBus::spawn() else {
+            return;
+        };
+        let (sender, _receiver) = channel(10);
+        let started = start_with_connection(sender, |x| x, bus.connect().await).await;
+        assert!(started.is_err());
+    }
+
+    #[test]
+    fn display_names() {
+        assert_eq!(display_name("power-saver"), "Power saver");
+        assert_eq!(display_name("balanced"), "Balanced");
+        assert_eq!(display_name(""), "");
+    }
+}
@@ -0,0 +1,484 @@
+use cosmic::{iced::futures::SinkExt, iced_futures::futures::channel::mpsc::Sender};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Read},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{
+    io::unix::AsyncFd,
+    task::JoinHandle,
+    time::{Interval, MissedTickBehavior},
+};
+
+const RFKILL_DEVICE: &str = "/dev/rfkill";
+const RFKILL_CLASS: &str = "class/rfkill";
+/// `struct rfkill_event` without the `hard_block_reasons` of newer kernels, short reads are fine.
+const EVENT_SIZE: usize = 8;
+const OP_ADD: u8 = 0;
+const OP_DEL: u8 = 1;
+const OP_CHANGE: u8 = 2;
+/// The airplane key blocks every device one after the other, the events arriving within this
+/// interval are handled together.
+const BATCH_INTERVAL: Duration = Duration::from_millis(100);
+/// sysfs is only read when `/dev/rfkill` cannot be opened, its files don't wake up inotify.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Every radio was blocked, or the first one was unblocked after that. Only sent with more
+    /// than one kind of radio, otherwise [`Event::Changed`] is sent.
+    AirplaneMode(bool),
+    /// Not sent for the state found on start.
+    Changed {
+        radio: Radio,
+        block: Block,
+    },
+    Error(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Radio {
+    Wlan,
+    Bluetooth,
+    Wwan,
+}
+
+impl Radio {
+    /// `RFKILL_TYPE_*` of `linux/rfkill.h`.
+    fn from_type(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::Wlan),
+            2 => Some(Self::Bluetooth),
+            5 => Some(Self::Wwan),
+            _ => None,
+        }
+    }
+    /// The `type` file in sysfs.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "wlan" => Some(Self::Wlan),
+            "bluetooth" => Some(Self::Bluetooth),
+            "wwan" => Some(Self::Wwan),
+            _ => None,
+        }
+    }
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Wlan => "Wi-Fi",
+            Self::Bluetooth => "Bluetooth",
+            Self::Wwan => "Mobile broadband",
+        }
+    }
+}
+
+/// A radio is blocked if any of its devices is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Block {
+    /// Blocked by software, e.g. the airplane key.
+    pub soft: bool,
+    /// Blocked by a hardware switch, cannot be undone from software.
+    pub hard: bool,
+}
+
+impl Block {
+    pub fn is_blocked(self) -> bool {
+        self.soft || self.hard
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Device {
+    radio: Radio,
+    block: Block,
+}
+
+enum Update {
+    Set(u32, Device),
+    Remove(u32),
+    /// Every device, read from sysfs.
+    Replace(HashMap<u32, Device>),
+}
+
+/// Where the updates come from.
+enum Source {
+    /// Non-blocking, so the task can be aborted while it waits.
+    Device(AsyncFd<File>),
+    Sysfs {
+        class: PathBuf,
+        interval: Interval,
+    },
+}
+
+impl Source {
+    /// An error means nothing can be read anymore. Cancel safe.
+    async fn next(&mut self) -> Result<Update, String> {
+        match self {
+            Self::Device(device) => read_device(device).await,
+            Self::Sysfs { class, interval } => {
+                interval.tick().await;
+                read_sysfs(class)
+                    .map(Update::Replace)
+                    .map_err(|e| format!("cannot read {}: {e}", class.display()))
+            }
+        }
+    }
+}
+
+/// Follows the radio blocks from a [`super::Registry`].
+pub struct RfkillMonitor {
+    /// Usually [`super::SYSFS_ROOT`], only read without `/dev/rfkill`.
+    pub root: PathBuf,
+}
+
+impl super::Monitor for RfkillMonitor {
+    type Event = Event;
+    type Error = io::Error;
+
+    fn name(&self) -> &'static str {
+        "rfkill"
+    }
+    /// Reads the events of `/dev/rfkill`, or polls `<root>/class/rfkill` if it cannot be opened,
+    /// `root` is usually [`super::SYSFS_ROOT`].
+    async fn start<T: Send + 'static>(
+        &self,
+        mut sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Result<JoinHandle<()>, io::Error> {
+        let device = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(RFKILL_DEVICE);
+        let mut source = match device {
+            // opening it queues an `OP_ADD` for every device
+            Ok(device) => Source::Device(AsyncFd::new(device)?),
+            Err(e) => {
+                tracing::info!("cannot open {RFKILL_DEVICE} ({e}), polling sysfs");
+                let class = self.root.join(RFKILL_CLASS);
+                // fails early without rfkill at all
+                read_sysfs(&class)?;
+                let mut interval = tokio::time::interval(POLL_INTERVAL);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                Source::Sysfs { class, interval }
+            }
+        };
+
+        Ok(tokio::spawn(async move {
+            let mut send = async |event| {
+                if let Err(e) = sender.send(map(event)).await {
+                    tracing::error!("Cannot send to sender: {e}");
+                }
+            };
+            let mut devices = HashMap::new();
+            let mut radios = None;
+            let mut error = None;
+            while error.is_none() {
+                match source.next().await {
+                    Ok(update) => apply(&mut devices, update),
+                    Err(e) => {
+                        error = Some(e);
+                        break;
+                    }
+                }
+                while let Ok(next) = tokio::time::timeout(BATCH_INTERVAL, source.next()).await {
+                    match next {
+                        Ok(update) => apply(&mut devices, update),
+                        Err(e) => {
+                            error = Some(e);
+                            break;
+                        }
+                    }
+                }
+                let new_radios = combine(&devices);
+                // the first batch is the state found on start
+                if let Some(old_radios) = radios.replace(new_radios.clone()) {
+                    for event in events(&old_radios, &new_radios) {
+                        send(event).await;
+                    }
+                }
+            }
+            if let Some(e) = error {
+                send(Event::Error(e)).await;
+            }
+        }))
+    }
+}
+
+/// The next event of `/dev/rfkill` we care about.
+async fn read_device(device: &AsyncFd<File>) -> Result<Update, String> {
+    let mut buffer = [0; EVENT_SIZE];
+    loop {
+        let mut guard = device
+            .readable()
+            .await
+            .map_err(|e| format!("cannot wait for {RFKILL_DEVICE}: {e}"))?;
+        let size = match guard.try_io(|x| x.get_ref().read(&mut buffer)) {
+            Ok(Ok(x)) => x,
+            Ok(Err(e)) => return Err(format!("cannot read {RFKILL_DEVICE}: {e}")),
+            // woken up for nothing
+            Err(_) => continue,
+        };
+        if size < EVENT_SIZE {
+            return Err(format!("{RFKILL_DEVICE} sent {size} bytes, not an event"));
+        }
+        if let Some(update) = parse_event(&buffer) {
+            return Ok(update);
+        }
+    }
+}
+
+/// An event of `/dev/rfkill`, `None` for the radios and the operations we don't care about.
+fn parse_event(event: &[u8; EVENT_SIZE]) -> Option<Update> {
+    let index = u32::from_ne_bytes([event[0], event[1], event[2], event[3]]);
+    match event[5] {
+        OP_DEL => Some(Update::Remove(index)),
+        OP_ADD | OP_CHANGE => {
+            let radio = Radio::from_type(event[4])?;
+            let block = Block {
+                soft: event[6] != 0,
+                hard: event[7] != 0,
+            };
+            Some(Update::Set(index, Device { radio, block }))
+        }
+        // `OP_CHANGE_ALL` is only written to the device, never read
+        _ => None,
+    }
+}
+
+/// The devices in `class/rfkill`, by the number in their name, e.g. `rfkill0`.
+fn read_sysfs(class: &Path) -> io::Result<HashMap<u32, Device>> {
+    let mut devices = HashMap::new();
+    for entry in fs::read_dir(class)? {
+        let path = entry?.path();
+        let read = |file: &str| fs::read_to_string(path.join(file)).map(|x| x.trim().to_owned());
+        let index = path
+            .file_name()
+            .and_then(|x| x.to_str()?.strip_prefix("rfkill")?.parse().ok());
+        let (Some(index), Ok(Some(radio))) = (index, read("type").map(|x| Radio::from_name(&x)))
+        else {
+            continue;
+        };
+        let block = match (read("soft"), read("hard")) {
+            (Ok(soft), Ok(hard)) => Block {
+                soft: soft != "0",
+                hard: hard != "0",
+            },
+            // removed while it was read
+            (Err(e), _) | (_, Err(e)) if e.kind() == io::ErrorKind::NotFound => continue,
+            (Err(e), _) | (_, Err(e)) => return Err(e),
+        };
+        devices.insert(index, Device { radio, block });
+    }
+    Ok(devices)
+}
+
+fn apply(devices: &mut HashMap<u32, Device>, update: Update) {
+    match update {
+        Update::Set(index, device) => {
+            devices.insert(index, device);
+        }
+        Update::Remove(index) => {
+            devices.remove(&index);
+        }
+        Update::Replace(new) => *devices = new,
+    }
+}
+
+fn combine(devices: &HashMap<u32, Device>) -> HashMap<Radio, Block> {
+    let mut radios = HashMap::<Radio, Block>::new();
+    for device in devices.values() {
+        let block = radios.entry(device.radio).or_default();
+        block.soft |= device.block.soft;
+        block.hard |= device.block.hard;
+    }
+    radios
+}
+
+fn events(old: &HashMap<Radio, Block>, new: &HashMap<Radio, Block>) -> Vec<Event> {
+    let airplane_mode = |radios: &HashMap<Radio, Block>| radios.values().all(|x| x.is_blocked());
+    if new.len() > 1 && old.len() == new.len() && airplane_mode(old) != airplane_mode(new) {
+        return vec![Event::AirplaneMode(airplane_mode(new))];
+    }
+    new.iter()
+        // a radio that just appeared is not a toggle
+        .filter(|(radio, block)| old.get(*radio).is_some_and(|x| x != *block))
+        .map(|(&radio, &block)| Event::Changed { radio, block })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPE_WLAN: u8 = 1;
+    const TYPE_BLUETOOTH: u8 = 2;
+    const TYPE_NFC: u8 = 8;
+    const OP_CHANGE_ALL: u8 = 3;
+
+    fn event(index: u32, radio: u8, op: u8, soft: bool, hard: bool) -> [u8; EVENT_SIZE] {
+        let [a, b, c, d] = index.to_ne_bytes();
+        [a, b, c, d, radio, op, soft as u8, hard as u8]
+    }
+
+    fn blocked(soft: bool, hard: bool) -> Block {
+        Block { soft, hard }
+    }
+
+    fn radios(radios: &[(Radio, Block)]) -> HashMap<Radio, Block> {
+        radios.iter().copied().collect()
+    }
+
+    #[test]
+    fn parse_events() {
+        let Some(Update::Set(3, device)) = parse_event(&event(3, TYPE_WLAN, OP_ADD, true, false))
+        else {
+            panic!("not added");
+        };
+        assert_eq!(device.radio, Radio::Wlan);
+        assert_eq!(device.block, blocked(true, false));
+        let changed = parse_event(&event(7, TYPE_BLUETOOTH, OP_CHANGE, false, true));
+        let Some(Update::Set(7, device)) = changed else {
+            panic!("not changed");
+        };
+        assert_eq!(device.radio, Radio::Bluetooth);
+        assert_eq!(device.block, blocked(false, true));
+        // the type of a removed device doesn't matter
+        let removed = parse_event(&event(3, TYPE_NFC, OP_DEL, false, false));
+        assert!(matches!(removed, Some(Update::Remove(3))));
+        assert!(parse_event(&event(4, TYPE_NFC, OP_ADD, false, false)).is_none());
+        assert!(parse_event(&event(3, TYPE_WLAN, OP_CHANGE_ALL, true, false)).is_none());
+    }
+
+    #[test]
+    fn apply_and_combine() {
+        let wlan = |soft, hard| Device {
+            radio: Radio::Wlan,
+            block: blocked(soft, hard),
+        };
+        let mut devices = HashMap::new();
+        // e.g. the Wi-Fi card and a USB dongle
+        apply(&mut devices, Update::Set(0, wlan(true, false)));
+        apply(&mut devices, Update::Set(1, wlan(false, true)));
+        assert_eq!(
+            combine(&devices),
+            radios(&[(Radio::Wlan, blocked(true, true))])
+        );
+        apply(&mut devices, Update::Set(1, wlan(false, false)));
+        apply(&mut devices, Update::Remove(0));
+        assert_eq!(
+            combine(&devices),
+            radios(&[(Radio::Wlan, blocked(false, false))])
+        );
+        let bluetooth = Device {
+            radio: Radio::Bluetooth,
+            block: blocked(true, false),
+        };
+        apply(
+            &mut devices,
+            Update::Replace(HashMap::from([(2, bluetooth)])),
+        );
+        assert_eq!(
+            combine(&devices),
+            radios(&[(Radio::Bluetooth, blocked(true, false))])
+        );
+        apply(&mut devices, Update::Remove(2));
+        assert!(combine(&devices).is_empty());
+    }
+
+    #[test]
+    fn airplane_mode() {
+        let on = radios(&[
+            (Radio::Wlan, blocked(true, false)),
+            (Radio::Bluetooth, blocked(true, false)),
+        ]);
+        let off = radios(&[
+            (Radio::Wlan, blocked(false, false)),
+            (Radio::Bluetooth, blocked(false, false)),
+        ]);
+        assert!(matches!(events(&off, &on)[..], [Event::AirplaneMode(true)]));
+        // the first radio back is the end of the airplane mode
+        let wlan_back = radios(&[
+            (Radio::Wlan, blocked(false, false)),
+            (Radio::Bluetooth, blocked(true, false)),
+        ]);
+        assert!(matches!(
+            events(&on, &wlan_back)[..],
+            [Event::AirplaneMode(false)]
+        ));
+        // a single radio is toggled on its own, not the airplane mode
+        assert!(matches!(
+            events(&wlan_back, &off)[..],
+            [Event::Changed {
+                radio: Radio::Bluetooth,
+                block: Block {
+                    soft: false,
+                    hard: false
+                }
+            }]
+        ));
+    }
+
+    #[test]
+    fn single_radio() {
+        let unblocked = radios(&[(Radio::Wlan, blocked(false, false))]);
+        let hard_blocked = radios(&[(Radio::Wlan, blocked(false, true))]);
+        assert!(matches!(
+            events(&unblocked, &hard_blocked)[..],
+            [Event::Changed {
+                radio: Radio::Wlan,
+                block: Block { hard: true, .. }
+            }]
+        ));
+        assert!(events(&unblocked, &unblocked).is_empty());
+    }
+
+    #[test]
+    fn radio_appearing() {
+        let wlan = radios(&[(Radio::Wlan, blocked(false, false))]);
+        // a Bluetooth dongle plugged in blocked is neither a toggle nor the airplane mode
+        let with_bluetooth = radios(&[
+            (Radio::Wlan, blocked(false, false)),
+            (Radio::Bluetooth, blocked(true, false)),
+        ]);
+        assert!(events(&wlan, &with_bluetooth).is_empty());
+        let all_blocked = radios(&[
+            (Radio::Wlan, blocked(true, false)),
+            (Radio::Bluetooth, blocked(true, false)),
+        ]);
+        assert!(events(&HashMap::new(), &all_blocked).is_empty());
+    }
+
+    #[test]
+    fn fake_sysfs() {
+        let root = tempfile::tempdir().unwrap();
+        let class = root.path().join(RFKILL_CLASS);
+        let device = |name: &str, files: &[(&str, &str)]| {
+            let path = class.join(name);
+            fs::create_dir_all(&path).unwrap();
+            for (file, value) in files {
+                fs::write(path.join(file), format!("{value}\n")).unwrap();
+            }
+        };
+        device("rfkill0", &[("type", "wlan"), ("soft", "1"), ("hard", "0")]);
+        device(
+            "rfkill1",
+            &[("type", "bluetooth"), ("soft", "0"), ("hard", "1")],
+        );
+        device("rfkill2", &[("type", "nfc"), ("soft", "0"), ("hard", "0")]);
+        // removed while it was read
+        device("rfkill3", &[("type", "wwan")]);
+        device("other", &[("type", "wlan"), ("soft", "0"), ("hard", "0")]);
+
+        let devices = read_sysfs(&class).unwrap();
+        let mut indexes = devices.keys().copied().collect::<Vec<_>>();
+        indexes.sort();
+        assert_eq!(indexes, [0, 1]);
+        assert_eq!(devices[&0].radio, Radio::Wlan);
+        assert_eq!(devices[&0].block, blocked(true, false));
+        assert_eq!(devices[&1].radio, Radio::Bluetooth);
+        assert_eq!(devices[&1].block, blocked(false, true));
+        assert!(read_sysfs(&root.path().join("missing")).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! What the tests of the monitors share.
+
+use cosmic::iced::futures::{StreamExt, channel::mpsc::Receiver};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    process::{self, Child, Stdio},
+    time::Duration,
+};
+use zbus::{Connection, connection::Builder, object_server::Interface};
+
+/// Long enough for a loaded CI machine, a passing test never waits that long.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+/// Anyone may own any name and talk to anyone, `{dir}` is where the socket goes.
+const BUS_CONFIG: &str = r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:dir={dir}</listen>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#;
+
+/// The next event, panics if none comes within [`TIMEOUT`].
+pub async fn next<T>(receiver: &mut Receiver<T>) -> T {
+    match tokio::time::timeout(TIMEOUT, receiver.next()).await {
+        Ok(Some(x)) => x,
+        Ok(None) => panic!("the events ended"),
+        Err(_) => panic!("no event in {TIMEOUT:?}"),
+    }
+}
+
+/// Writes a sysfs file in place like the kernel does, truncating it first would show an empty
+/// file to a monitor reading it meanwhile.
+pub fn write_in_place(path: &Path, value: &str) {
+    fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .write_all(value.as_bytes())
+        .unwrap();
+}
+
+/// `None` if the program isn't installed, the test is skipped then.
+pub fn spawn_or_skip(command: &mut process::Command) -> Option<Child> {
+    match command.spawn() {
+        Ok(x) => Some(x),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            eprintln!("skipped, cannot run {:?}: {e}", command.get_program());
+            None
+        }
+        Err(e) => panic!("cannot run {:?}: {e}", command.get_program()),
+    }
+}
+
+/// A `dbus-daemon` of our own, stubs take the names of the system services on it. Killed on drop.
+pub struct PrivateBus {
+    daemon: Child,
+    address: String,
+    _dir: tempfile::TempDir,
+}
+
+impl PrivateBus {
+    /// `None` if `dbus-daemon` isn't installed.
+    pub fn spawn() -> Option<Self> {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("bus.conf");
+        let text = BUS_CONFIG.replace("{dir}", dir.path().to_str().unwrap());
+        fs::write(&config, text).unwrap();
+        let mut daemon = spawn_or_skip(
+            process::Command::new("dbus-daemon")
+                .arg(format!("--config-file={}", config.display()))
+                .args(["--nofork", "--print-address"])
+                .stdout(Stdio::piped()),
+        )?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        Some(Self {
+            daemon,
+            address: address.trim().to_owned(),
+            _dir: dir,
+        })
+    }
+    pub async fn connect(&self) -> Connection {
+        self.builder().build().await.unwrap()
+    }
+    /// A connection owning `name` with `stub` at `path`, the stub is gone with the connection.
+    pub async fn serve(&self, name: &str, path: &str, stub: impl Interface) -> Connection {
+        self.builder()
+            .name(name)
+            .unwrap()
+            .serve_at(path, stub)
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+    fn builder(&self) -> Builder<'_> {
+        Builder::address(self.address.as_str()).unwrap()
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
@@ -0,0 +1,688 @@
+use super::{Interfaces, Properties, get, subscribe};
+use cosmic::{
+    iced::futures::SinkExt,
+    iced_futures::futures::{TryStreamExt, channel::mpsc::Sender},
+};
+use std::{collections::HashMap, ffi::OsStr, os::unix::ffi::OsStrExt, path::PathBuf};
+use tokio::task::JoinHandle;
+use zbus::{
+    Connection, MatchRule, Proxy,
+    zvariant::{OwnedObjectPath, OwnedValue},
+};
+
+const UDISKS: &str = "org.freedesktop.UDisks2";
+const UDISKS_PATH: &str = "/org/freedesktop/UDisks2";
+const OBJECT_MANAGER_INTERFACE: &str = "org.freedesktop.DBus.ObjectManager";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const BLOCK_INTERFACE: &str = "org.freedesktop.UDisks2.Block";
+const FILESYSTEM_INTERFACE: &str = "org.freedesktop.UDisks2.Filesystem";
+const DRIVE_INTERFACE: &str = "org.freedesktop.UDisks2.Drive";
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Sent once on start, the controller ejects the media.
+    Ready(Controller),
+    /// A filesystem of a removable drive was mounted.
+    Mounted(Mount),
+    /// It was unmounted or the drive was pulled out, [`Mount::mount_point`] is the old one.
+    Unmounted(Mount),
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mount {
+    /// The object path of the block device, e.g. "/org/freedesktop/UDisks2/block_devices/sdb1".
+    pub block: String,
+    /// The object path of the drive, `None` for e.g. a loop device.
+    pub drive: Option<String>,
+    /// The vendor or the model of the drive and the size, e.g. "SanDisk 64 GB".
+    pub name: String,
+    pub mount_point: PathBuf,
+}
+
+/// The part of a block device we care about, only one with a filesystem has mount points.
+#[derive(Debug, Clone, Default)]
+struct Block {
+    drive: Option<String>,
+    size: u64,
+    /// Internal disks, their mounts are not shown.
+    system: bool,
+    mount_points: Vec<PathBuf>,
+}
+
+impl Block {
+    fn update(&mut self, properties: &Properties) {
+        if let Some(drive) = get::<OwnedObjectPath>(properties, "Drive") {
+            self.drive = Some(drive.to_string()).filter(|x| x != "/");
+        }
+        if let Some(size) = get(properties, "Size") {
+            self.size = size;
+        }
+        if let Some(system) = get(properties, "HintSystem") {
+            self.system = system;
+        }
+    }
+    fn update_mount_points(&mut self, properties: &Properties) {
+        if let Some(mount_points) = get::<Vec<Vec<u8>>>(properties, "MountPoints") {
+            self.mount_points = mount_points.iter().map(|x| mount_point(x)).collect();
+        }
+    }
+}
+
+/// Ejects the media through UDisks, as the user of the session.
+#[derive(Debug, Clone)]
+pub struct Controller {
+    connection: Connection,
+}
+
+impl Controller {
+    /// Unmounts every filesystem of the drive, not only `mount`, then powers the drive off so it
+    /// can be pulled out.
+    pub async fn eject(&self, mount: &Mount) -> zbus::Result<()> {
+        let Some(drive) = &mount.drive else {
+            return self.unmount(&mount.block).await;
+        };
+        let proxy = Proxy::new(
+            &self.connection,
+            UDISKS,
+            UDISKS_PATH,
+            OBJECT_MANAGER_INTERFACE,
+        )
+        .await?;
+        let objects: HashMap<OwnedObjectPath, Interfaces> =
+            proxy.call("GetManagedObjects", &()).await?;
+        let mut blocks = HashMap::new();
+        let mut drives = HashMap::new();
+        for (path, interfaces) in &objects {
+            add_interfaces(&mut blocks, &mut drives, path.as_str(), interfaces);
+        }
+        let mounted = blocks
+            .iter()
+            .filter(|(_, x)| x.drive.as_ref() == Some(drive) && !x.mount_points.is_empty());
+        for (path, _) in mounted {
+            self.unmount(path).await?;
+        }
+        let drive = Proxy::new(&self.connection, UDISKS, drive.as_str(), DRIVE_INTERFACE).await?;
+        drive
+            .call("PowerOff", &(&HashMap::<String, OwnedValue>::new(),))
+            .await
+    }
+    async fn unmount(&self, block: &str) -> zbus::Result<()> {
+        let filesystem = Proxy::new(&self.connection, UDISKS, block, FILESYSTEM_INTERFACE).await?;
+        filesystem
+            .call("Unmount", &(&HashMap::<String, OwnedValue>::new(),))
+            .await
+    }
+}
+
+/// Follows UDisks on the system bus from a [`super::Registry`].
+pub struct UDisksMonitor;
+
+impl super::Monitor for UDisksMonitor {
+    type Event = Event;
+    type Error = zbus::Error;
+
+    fn name(&self) -> &'static str {
+        "udisks"
+    }
+    async fn start<T: Send + 'static>(
+        &self,
+        sender: Sender<T>,
+        map: impl Fn(Event) -> T + Clone + Send + Sync + 'static,
+    ) -> Result<JoinHandle<()>, zbus::Error> {
+        start_with_connection(sender, map, Connection::system().await?).await
+    }
+}
+
+/// Any bus with an `org.freedesktop.UDisks2` on it, e.g. a private bus with a stub in tests.
+async fn start_with_connection<T: Send + 'static>(
+    mut sender: Sender<T>,
+    map: impl Fn(Event) -> T + Send + Sync + 'static,
+    connection: Connection,
+) -> Result<JoinHandle<()>, zbus::Error> {
+    let rule = MatchRule::builder()
+        .msg_type(zbus::message::Type::Signal)
+        .sender(UDISKS)?
+        .build();
+    let mut stream = subscribe(&connection, rule).await?;
+    let proxy = Proxy::new(&connection, UDISKS, UDISKS_PATH, OBJECT_MANAGER_INTERFACE).await?;
+    let objects: HashMap<OwnedObjectPath, Interfaces> =
+        proxy.call("GetManagedObjects", &()).await?;
+    let mut blocks = HashMap::new();
+    // the name of every drive
+    let mut drives = HashMap::new();
+    for (path, interfaces) in &objects {
+        add_interfaces(&mut blocks, &mut drives, path.as_str(), interfaces);
+    }
+    let controller = Controller {
+        connection: connection.clone(),
+    };
+
+    Ok(tokio::spawn(async move {
+        let mut send = async |event| {
+            if let Err(e) = sender.send(map(event)).await {
+                tracing::error!("Cannot send to sender: {e}");
+            }
+        };
+        send(Event::Ready(controller)).await;
+        loop {
+            let message = match stream.try_next().await {
+                Ok(Some(x)) => x,
+                Ok(None) => {
+                    tracing::info!("message stream ended");
+                    break;
+                }
+                Err(e) => {
+                    tracing::error!("error: {e}");
+                    break;
+                }
+            };
+            let header = message.header();
+            let body = message.body();
+            let events = match (
+                header.interface().map(|x| x.as_str()),
+                header.member().map(|x| x.as_str()),
+            ) {
+                (Some(OBJECT_MANAGER_INTERFACE), Some("InterfacesAdded")) => {
+                    match body.deserialize::<(OwnedObjectPath, Interfaces)>() {
+                        Ok((path, interfaces)) => {
+                            let before = blocks.get(path.as_str()).cloned();
+                            add_interfaces(&mut blocks, &mut drives, path.as_str(), &interfaces);
+                            compare(
+                                path.as_str(),
+                                before.as_ref(),
+                                blocks.get(path.as_str()),
+                                &drives,
+                            )
+                        }
+                        Err(e) => vec![Event::Error(format!("deserialize error: {e}"))],
+                    }
+                }
+                (Some(OBJECT_MANAGER_INTERFACE), Some("InterfacesRemoved")) => {
+                    match body.deserialize::<(OwnedObjectPath, Vec<String>)>() {
+                        Ok((path, interfaces)) => {
+                            let path = path.as_str();
+                            let before = blocks.get(path).cloned();
+                            if interfaces.iter().any(|x| x == BLOCK_INTERFACE) {
+                                blocks.remove(path);
+                            } else if interfaces.iter().any(|x| x == FILESYSTEM_INTERFACE)
+                                && let Some(block) = blocks.get_mut(path)
+                            {
+                                block.mount_points.clear();
+                            }
+                            // e.g. pulled out while mounted
+                            let events = compare(path, before.as_ref(), blocks.get(path), &drives);
+                            if interfaces.iter().any(|x| x == DRIVE_INTERFACE) {
+                                drives.remove(path);
+                            }
+                            events
+                        }
+                        Err(e) => vec![Event::Error(format!("deserialize error: {e}"))],
+                    }
+                }
+                (Some(PROPERTIES_INTERFACE), Some("PropertiesChanged")) => {
+                    let Some(path) = header.path().map(|x| x.to_string()) else {
+                        continue;
+                    };
+                    match body.deserialize::<(String, Properties, Vec<String>)>() {
+                        Ok((interface, changed, _)) => {
+                            let before = blocks.get(&path).cloned();
+                            if let Some(block) = blocks.get_mut(&path) {
+                                match interface.as_str() {
+                                    BLOCK_INTERFACE => block.update(&changed),
+                                    FILESYSTEM_INTERFACE => block.update_mount_points(&changed),
+                                    _ => (),
+                                }
+                            }
+                            compare(&path, before.as_ref(), blocks.get(&path), &drives)
+                        }
+                        Err(e) => vec![Event::Error(format!("deserialize error: {e}"))],
+                    }
+                }
+                _ => Vec::new(),
+            };
+            for event in events {
+                send(event).await;
+            }
+        }
+    }))
+}
+
+/// Adds the block, its filesystem or the drive at `path`, a filesystem created on a block that
+/// is already there comes alone.
+fn add_interfaces(
+    blocks: &mut HashMap<String, Block>,
+    drives: &mut HashMap<String, String>,
+    path: &str,
+    interfaces: &Interfaces,
+) {
+    let block = interfaces.get(BLOCK_INTERFACE);
+    let filesystem = interfaces.get(FILESYSTEM_INTERFACE);
+    if block.is_some() || filesystem.is_some() {
+        let entry = blocks.entry(path.to_owned()).or_default();
+        if let Some(block) = block {
+            entry.update(block);
+        }
+        if let Some(filesystem) = filesystem {
+            entry.update_mount_points(filesystem);
+        }
+    }
+    if let Some(drive) = interfaces.get(DRIVE_INTERFACE) {
+        let vendor = get::<String>(drive, "Vendor").unwrap_or_default();
+        let model = get::<String>(drive, "Model").unwrap_or_default();
+        // card readers often have no vendor
+        let name = if vendor.trim().is_empty() {
+            model
+        } else {
+            vendor
+        };
+        drives.insert(path.to_owned(), name.trim().to_owned());
+    }
+}
+
+/// The events of the block at `path` going from `before` to `after`.
+fn compare(
+    path: &str,
+    before: Option<&Block>,
+    after: Option<&Block>,
+    drives: &HashMap<String, String>,
+) -> Vec<Event> {
+    let Some(block) = after.or(before) else {
+        return Vec::new();
+    };
+    if block.system {
+        return Vec::new();
+    }
+    let before = before.map_or(&[][..], |x| &x.mount_points[..]);
+    let after = after.map_or(&[][..], |x| &x.mount_points[..]);
+    let mount = |mount_point: &PathBuf| Mount {
+        block: path.to_owned(),
+        drive: block.drive.clone(),
+        name: name(block, drives),
+        mount_point: mount_point.clone(),
+    };
+    let unmounted = before.iter().filter(|x| !after.contains(*x));
+    let mounted = after.iter().filter(|x| !before.contains(*x));
+    unmounted
+        .map(|x| Event::Unmounted(mount(x)))
+        .chain(mounted.map(|x| Event::Mounted(mount(x))))
+        .collect()
+}
+
+fn name(block: &Block, drives: &HashMap<String, String>) -> String {
+    let drive = block
+        .drive
+        .as_ref()
+        .and_then(|x| drives.get(x))
+        .filter(|x| !x.is_empty());
+    match drive {
+        Some(drive) => format!("{drive} {}", format_size(block.size)),
+        None => format!("{} drive", format_size(block.size)),
+    }
+}
+
+/// In the decimal units drives are sold in, e.g. "64 GB".
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if size < 10.0 && unit > 0 {
+        format!("{size:.1} {}", UNITS[unit])
+    } else {
+        format!("{size:.0} {}", UNITS[unit])
+    }
+}
+
+/// `MountPoints` are NUL terminated byte strings.
+fn mount_point(bytes: &[u8]) -> PathBuf {
+    let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+    PathBuf::from(OsStr::from_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::test_util::{PrivateBus, next},
+        *,
+    };
+    use cosmic::iced::futures::channel::mpsc::{Receiver, channel};
+    use std::sync::{Arc, Mutex};
+    use zbus::{fdo::ObjectManager, message::Header, object_server::SignalEmitter};
+
+    const STICK: &str = "/org/freedesktop/UDisks2/drives/SanDisk_Cruzer";
+    const OTHER_STICK: &str = "/org/freedesktop/UDisks2/drives/Kingston_DataTraveler";
+    const SDB1: &str = "/org/freedesktop/UDisks2/block_devices/sdb1";
+    const SDB2: &str = "/org/freedesktop/UDisks2/block_devices/sdb2";
+    const SDC1: &str = "/org/freedesktop/UDisks2/block_devices/sdc1";
+    const NVME: &str = "/org/freedesktop/UDisks2/block_devices/nvme0n1p2";
+
+    /// The methods called on the stubs, with the object path.
+    type Calls = Arc<Mutex<Vec<(&'static str, String)>>>;
+
+    struct BlockDevice {
+        drive: &'static str,
+        size: u64,
+        system: bool,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.UDisks2.Block")]
+    impl BlockDevice {
+        #[zbus(property)]
+        fn drive(&self) -> OwnedObjectPath {
+            OwnedObjectPath::try_from(self.drive).unwrap()
+        }
+        #[zbus(property)]
+        fn size(&self) -> u64 {
+            self.size
+        }
+        #[zbus(property)]
+        fn hint_system(&self) -> bool {
+            self.system
+        }
+    }
+
+    struct Filesystem {
+        mount_points: Vec<Vec<u8>>,
+        calls: Calls,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.UDisks2.Filesystem")]
+    impl Filesystem {
+        #[zbus(property)]
+        fn mount_points(&self) -> Vec<Vec<u8>> {
+            self.mount_points.clone()
+        }
+        async fn unmount(
+            &mut self,
+            _options: HashMap<String, OwnedValue>,
+            #[zbus(header)] header: Header<'_>,
+            #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        ) -> zbus::fdo::Result<()> {
+            let path = header.path().map(|x| x.to_string()).unwrap_or_default();
+            self.calls.lock().unwrap().push(("Unmount", path));
+            self.mount_points.clear();
+            self.mount_points_changed(&emitter).await?;
+            Ok(())
+        }
+    }
+
+    struct Drive {
+        vendor: &'static str,
+        calls: Calls,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.UDisks2.Drive")]
+    impl Drive {
+        #[zbus(property)]
+        fn vendor(&self) -> String {
+            self.vendor.to_owned()
+        }
+        #[zbus(property)]
+        fn model(&self) -> String {
+            "Flash Drive".to_owned()
+        }
+        fn power_off(
+            &self,
+            _options: HashMap<String, OwnedValue>,
+            #[zbus(header)] header: Header<'_>,
+        ) {
+            let path = header.path().map(|x| x.to_string()).unwrap_or_default();
+            self.calls.lock().unwrap().push(("PowerOff", path));
+        }
+    }
+
+    /// A partition of `drive` with a filesystem, each interface comes in its own
+    /// `InterfacesAdded`.
+    async fn add_partition(
+        udisks: &Connection,
+        path: &str,
+        drive: &'static str,
+        mount_point: Option<&str>,
+        calls: &Calls,
+    ) {
+        let block = BlockDevice {
+            drive,
+            size: 64_000_000_000,
+            // the internal disk, its drive doesn't matter here
+            system: drive == "/",
+        };
+        let filesystem = Filesystem {
+            mount_points: mount_point.map(mount_point_bytes).into_iter().collect(),
+            calls: calls.clone(),
+        };
+        udisks.object_server().at(path, block).await.unwrap();
+        udisks.object_server().at(path, filesystem).await.unwrap();
+    }
+
+    async fn add_drive(udisks: &Connection, path: &str, vendor: &'static str, calls: &Calls) {
+        let drive = Drive {
+            vendor,
+            calls: calls.clone(),
+        };
+        udisks.object_server().at(path, drive).await.unwrap();
+    }
+
+    /// Mounted by someone else, e.g. the file manager.
+    async fn set_mount_point(udisks: &Connection, path: &str, mount_point: Option<&str>) {
+        let stub = udisks
+            .object_server()
+            .interface::<_, Filesystem>(path)
+            .await
+            .unwrap();
+        stub.get_mut().await.mount_points =
+            mount_point.map(mount_point_bytes).into_iter().collect();
+        let emitter = stub.signal_emitter();
+        stub.get()
+            .await
+            .mount_points_changed(emitter)
+            .await
+            .unwrap();
+    }
+
+    fn mount_point_bytes(mount_point: &str) -> Vec<u8> {
+        [mount_point.as_bytes(), b"\0"].concat()
+    }
+
+    fn block(drive: Option<&str>, system: bool, mount_points: &[&str]) -> Block {
+        Block {
+            drive: drive.map(|x| x.to_owned()),
+            size: 8_000_000_000,
+            system,
+            mount_points: mount_points.iter().map(PathBuf::from).collect(),
+        }
+    }
+
+    fn drives() -> HashMap<String, String> {
+        HashMap::from([
+            (STICK.to_owned(), "SanDisk".to_owned()),
+            (OTHER_STICK.to_owned(), String::new()),
+        ])
+    }
+
+    async fn next_mount(receiver: &mut Receiver<Event>) -> (bool, Mount) {
+        match next(receiver).await {
+            Event::Mounted(x) => (true, x),
+            Event::Unmounted(x) => (false, x),
+            Event::Ready(_) => panic!("ready again"),
+            Event::Error(e) => panic!("{e}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn mounts_of_a_stick() {
+        let Some(bus) = PrivateBus::spawn() else {
+            return;
+        };
+        let calls = Calls::default();
+        let udisks = bus.serve(UDISKS, UDISKS_PATH, ObjectManager).await;
+        add_partition(&udisks, NVME, "/", Some("/"), &calls).await;
+        let (sender, mut receiver) = channel(10);
+        let handle = start_with_connection(sender, |x| x, bus.connect().await)
+            .await
+            .unwrap();
+        let Event::Ready(_) = next(&mut receiver).await else {
+            panic!("not ready");
+        };
+
+        // plugged in, then mounted
+        add_drive(&udisks, STICK, "SanDisk", &calls).await;
+        add_partition(&udisks, SDB1, STICK, None, &calls).await;
+        // the internal disk is never shown
+        set_mount_point(&udisks, NVME, None).await;
+        set_mount_point(&udisks, SDB1, Some("/run/media/user/STICK")).await;
+        let (mounted, mount) = next_mount(&mut receiver).await;
+        assert!(mounted);
+        assert_eq!(
+            mount,
+            Mount {
+                block: SDB1.to_owned(),
+                drive: Some(STICK.to_owned()),
+                name: "SanDisk 64 GB".to_owned(),
+                mount_point: "/run/media/user/STICK".into(),
+            }
+        );
+
+        // pulled out while mounted, the filesystem goes first
+        udisks
+            .object_server()
+            .remove::<Filesystem, _>(SDB1)
+            .await
+            .unwrap();
+        udisks
+            .object_server()
+            .remove::<BlockDevice, _>(SDB1)
+            .await
+            .unwrap();
+        let (mounted, mount) = next_mount(&mut receiver).await;
+        assert!(!mounted);
+        assert_eq!(mount.mount_point, PathBuf::from("/run/media/user/STICK"));
+
+        // a stick plugged in already mounted
+        add_partition(&udisks, SDB2, STICK, Some("/run/media/user/DATA"), &calls).await;
+        let (mounted, mount) = next_mount(&mut receiver).await;
+        assert!(mounted);
+        assert_eq!(mount.block, SDB2);
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn eject_unmounts_every_partition_of_the_drive() {
+        let Some(bus) = PrivateBus::spawn() else {
+            return;
+        };
+        let calls = Calls::default();
+        let udisks = bus.serve(UDISKS, UDISKS_PATH, ObjectManager).await;
+        add_drive(&udisks, STICK, "SanDisk", &calls).await;
+        add_drive(&udisks, OTHER_STICK, "Kingston", &calls).await;
+        add_partition(&udisks, SDB1, STICK, Some("/run/media/user/BOOT"), &calls).await;
+        add_partition(&udisks, SDB2, STICK, Some("/run/media/user/DATA"), &calls).await;
+        add_partition(
+            &udisks,
+            SDC1,
+            OTHER_STICK,
+            Some("/run/media/user/KEY"),
+            &calls,
+        )
+        .await;
+        let controller = Controller {
+            connection: bus.connect().await,
+        };
+
+        let mount = Mount {
+            block: SDB1.to_owned(),
+            drive: Some(STICK.to_owned()),
+            name: "SanDisk 64 GB".to_owned(),
+            mount_point: "/run/media/user/BOOT".into(),
+        };
+        controller.eject(&mount).await.unwrap();
+        let mut calls = calls.lock().unwrap().clone();
+        // the partitions are unmounted in any order, the drive is powered off last
+        assert_eq!(calls.pop(), Some(("PowerOff", STICK.to_owned())));
+        calls.sort();
+        assert_eq!(
+            calls,
+            [("Unmount", SDB1.to_owned()), ("Unmount", SDB2.to_owned())]
+        );
+    }
+
+    #[test]
+    fn compare_mount_points() {
+        let drives = drives();
+        let unmounted = block(Some(STICK), false, &[]);
+        let mounted = block(Some(STICK), false, &["/run/media/user/STICK"]);
+        let events = compare(SDB1, Some(&unmounted), Some(&mounted), &drives);
+        let [Event::Mounted(mount)] = &events[..] else {
+            panic!("not mounted");
+        };
+        assert_eq!(mount.block, SDB1);
+        assert_eq!(mount.drive.as_deref(), Some(STICK));
+        assert_eq!(mount.name, "SanDisk 8.0 GB");
+
+        // gone while mounted
+        let events = compare(SDB1, Some(&mounted), None, &drives);
+        let [Event::Unmounted(mount)] = &events[..] else {
+            panic!("not unmounted");
+        };
+        assert_eq!(mount.mount_point, PathBuf::from("/run/media/user/STICK"));
+
+        // moved, the old mount point goes first
+        let moved = block(Some(STICK), false, &["/mnt"]);
+        let events = compare(SDB1, Some(&mounted), Some(&moved), &drives);
+        let [Event::Unmounted(old), Event::Mounted(new)] = &events[..] else {
+            panic!("not moved: {events:?}");
+        };
+        assert_eq!(old.mount_point, PathBuf::from("/run/media/user/STICK"));
+        assert_eq!(new.mount_point, PathBuf::from("/mnt"));
+
+        assert!(compare(SDB1, Some(&mounted), Some(&mounted), &drives).is_empty());
+        assert!(compare(SDB1, None, None, &drives).is_empty());
+        let system = block(None, true, &["/"]);
+        assert!(compare(NVME, None, Some(&system), &drives).is_empty());
+    }
+
+    #[test]
+    fn compare_names() {
+        let drives = drives();
+        let name = |drive| {
+            let block = block(drive, false, &["/mnt"]);
+            match &compare(SDB1, None, Some(&block), &drives)[..] {
+                [Event::Mounted(mount)] => mount.name.clone(),
+                events => panic!("not mounted: {events:?}"),
+            }
+        };
+        assert_eq!(name(Some(STICK)), "SanDisk 8.0 GB");
+        // no vendor nor model
+        assert_eq!(name(Some(OTHER_STICK)), "8.0 GB drive");
+        // e.g. a loop device
+        assert_eq!(name(None), "8.0 GB drive");
+    }
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(999), "999 B");
+        assert_eq!(format_size(1000), "1.0 kB");
+        assert_eq!(format_size(8_000_000_000), "8.0 GB");
+        assert_eq!(format_size(15_400_000_000), "15 GB");
+        assert_eq!(format_size(64_000_000_000), "64 GB");
+        assert_eq!(format_size(2_500_000_000_000), "2.5 TB");
+        // there is no bigger unit
+        assert_eq!(format_size(5_000_000_000_000_000), "5000 TB");
+    }
+
+    #[test]
+    fn mount_points() {
+        assert_eq!(
+            mount_point(b"/run/media/user/STICK\0"),
+            PathBuf::from("/run/media/user/STICK")
+        );
+        assert_eq!(mount_point(b"/mnt"), PathBuf::from("/mnt"));
+        // not UTF-8, the bytes are kept as they are
+        let path = mount_point(b"/mnt/\xff\0");
+        assert_eq!(path.as_os_str().as_bytes(), b"/mnt/\xff");
+    }
+}
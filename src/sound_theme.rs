@@ -0,0 +1,329 @@
+use crate::monitor::pipewire::Sample;
+use lewton::{inside_ogg::OggStreamReader, samples::InterleavedSamples};
+use std::{
+    env, fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
+
+/// Searched after every other theme, as the sound theme spec asks.
+const FALLBACK_THEME: &str = "freedesktop";
+const EXTENSIONS: &[&str] = &["oga", "ogg", "wav"];
+/// Used when the theme has no sample we can read.
+const CLICK_RATE: u32 = 48000;
+const CLICK_DURATION_MS: u32 = 30;
+const CLICK_FREQUENCY: f32 = 1000.0;
+const CLICK_AMPLITUDE: f32 = 0.3;
+
+/// Loads the sample `name` (e.g. "audio-volume-change") of the sound theme `theme` or of the
+/// themes it inherits, falls back to a short click. Ogg Vorbis and PCM WAV files are supported.
+pub fn load(theme: &str, name: &str) -> Sample {
+    let dirs = data_dirs();
+    for path in sample_paths(&dirs, &themes(&dirs, theme), name) {
+        let Ok(bytes) = fs::read(&path) else {
+            continue;
+        };
+        let sample = match path.extension().and_then(|x| x.to_str()) {
+            Some("wav") => parse_wav(&bytes),
+            _ => decode_vorbis(&bytes),
+        };
+        match sample {
+            Some(sample) => return sample,
+            None => tracing::warn!("cannot read {}", path.display()),
+        }
+    }
+    tracing::info!("no {name} sample in the sound theme {theme}, using a click");
+    click()
+}
+
+/// `$XDG_DATA_HOME` then `$XDG_DATA_DIRS`.
+fn data_dirs() -> Vec<PathBuf> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|x| PathBuf::from(x).join(".local/share")));
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|x| !x.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_owned());
+    data_home
+        .into_iter()
+        .chain(data_dirs.split(':').map(PathBuf::from))
+        .collect()
+}
+
+/// `theme` then the themes it inherits breadth first, [`FALLBACK_THEME`] last.
+fn themes(dirs: &[PathBuf], theme: &str) -> Vec<String> {
+    let mut themes = vec![theme.to_owned()];
+    let mut index = 0;
+    while let Some(theme) = themes.get(index).cloned() {
+        // the first `index.theme` found is the one of the theme
+        let index_theme = dirs
+            .iter()
+            .find_map(|dir| fs::read_to_string(theme_dir(dir, &theme).join("index.theme")).ok());
+        for parent in index_theme.as_deref().map(inherits).unwrap_or_default() {
+            if !themes.contains(&parent) {
+                themes.push(parent);
+            }
+        }
+        index += 1;
+    }
+    if !themes.iter().any(|x| x == FALLBACK_THEME) {
+        themes.push(FALLBACK_THEME.to_owned());
+    }
+    themes
+}
+
+/// The `Inherits` key of the `[Sound Theme]` group, a comma separated list.
+fn inherits(index_theme: &str) -> Vec<String> {
+    let mut group = "";
+    for line in index_theme.lines().map(str::trim) {
+        if let Some(x) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+            group = x;
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if group == "Sound Theme" && key.trim() == "Inherits" {
+            return value
+                .split(',')
+                .map(str::trim)
+                .filter(|x| !x.is_empty())
+                .map(str::to_owned)
+                .collect();
+        }
+    }
+    Vec::new()
+}
+
+fn theme_dir(data_dir: &Path, theme: &str) -> PathBuf {
+    data_dir.join("sounds").join(theme)
+}
+
+/// `<data dir>/sounds/<theme>/stereo/<name>.<extension>`, every data dir of a theme before the
+/// next theme.
+fn sample_paths(dirs: &[PathBuf], themes: &[String], name: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for theme in themes {
+        for dir in dirs {
+            for extension in EXTENSIONS {
+                paths.push(
+                    theme_dir(dir, theme)
+                        .join("stereo")
+                        .join(format!("{name}.{extension}")),
+                );
+            }
+        }
+    }
+    paths
+}
+
+/// Ogg Vorbis, the freedesktop theme ships its samples as `.oga`.
+fn decode_vorbis(bytes: &[u8]) -> Option<Sample> {
+    let mut reader = OggStreamReader::new(Cursor::new(bytes)).ok()?;
+    let mut samples = Vec::new();
+    while let Some(packet) = reader
+        .read_dec_packet_generic::<InterleavedSamples<f32>>()
+        .ok()?
+    {
+        samples.extend(packet.samples);
+    }
+    Some(Sample {
+        rate: reader.ident_hdr.audio_sample_rate,
+        channels: reader.ident_hdr.audio_channels.into(),
+        samples,
+    })
+}
+
+/// 16 bit integer or 32 bit float PCM.
+fn parse_wav(bytes: &[u8]) -> Option<Sample> {
+    let u16_at =
+        |x: &[u8], at: usize| Some(u16::from_le_bytes(x.get(at..at + 2)?.try_into().ok()?));
+    let u32_at =
+        |x: &[u8], at: usize| Some(u32::from_le_bytes(x.get(at..at + 4)?.try_into().ok()?));
+    if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" {
+        return None;
+    }
+
+    let mut format = None;
+    let mut offset = 12;
+    while let Some(id) = bytes.get(offset..offset + 4) {
+        let size = u32_at(bytes, offset + 4)? as usize;
+        let chunk = bytes.get(offset + 8..(offset + 8 + size).min(bytes.len()))?;
+        match id {
+            b"fmt " => {
+                // (format tag, channels, rate, bits per sample)
+                format = Some((
+                    u16_at(chunk, 0)?,
+                    u16_at(chunk, 2)?,
+                    u32_at(chunk, 4)?,
+                    u16_at(chunk, 14)?,
+                ));
+            }
+            b"data" => {
+                let (tag, channels, rate, bits) = format?;
+                let samples = match (tag, bits) {
+                    (1, 16) => chunk
+                        .chunks_exact(2)
+                        .map(|x| i16::from_le_bytes([x[0], x[1]]) as f32 / i16::MAX as f32)
+                        .collect(),
+                    (3, 32) => chunk
+                        .chunks_exact(4)
+                        .map(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+                        .collect(),
+                    _ => return None,
+                };
+                return Some(Sample {
+                    rate,
+                    channels: channels.into(),
+                    samples,
+                });
+            }
+            _ => (),
+        }
+        // chunks are padded to an even size
+        offset += 8 + size + size % 2;
+    }
+    None
+}
+
+/// A short sine burst that fades out.
+fn click() -> Sample {
+    let count = CLICK_RATE * CLICK_DURATION_MS / 1000;
+    let samples = (0..count)
+        .map(|i| {
+            let time = i as f32 / CLICK_RATE as f32;
+            let fade = 1.0 - i as f32 / count as f32;
+            (time * CLICK_FREQUENCY * std::f32::consts::TAU).sin() * CLICK_AMPLITUDE * fade
+        })
+        .collect();
+    Sample {
+        rate: CLICK_RATE,
+        channels: 1,
+        samples,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mono 8 kHz Vorbis, three packets of silence after the headers.
+    const SILENCE_OGA: &[u8] = include_bytes!("../tests/fixtures/silence.oga");
+
+    /// A `fmt ` chunk, an odd sized chunk we don't know with its padding, then `data`.
+    fn wav(tag: u16, channels: u16, rate: u32, bits: u16, data: &[u8]) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut fmt = Vec::new();
+        fmt.extend(tag.to_le_bytes());
+        fmt.extend(channels.to_le_bytes());
+        fmt.extend(rate.to_le_bytes());
+        fmt.extend((rate * block_align as u32).to_le_bytes());
+        fmt.extend(block_align.to_le_bytes());
+        fmt.extend(bits.to_le_bytes());
+        let mut chunks = Vec::new();
+        for (id, chunk) in [(b"fmt ", &fmt[..]), (b"LIST", b"abc"), (b"data", data)] {
+            chunks.extend(id);
+            chunks.extend((chunk.len() as u32).to_le_bytes());
+            chunks.extend(chunk);
+            if chunk.len() % 2 == 1 {
+                chunks.push(0);
+            }
+        }
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend((4 + chunks.len() as u32).to_le_bytes());
+        bytes.extend(b"WAVE");
+        bytes.extend(chunks);
+        bytes
+    }
+
+    #[test]
+    fn pcm_wav() {
+        let data = [0i16, i16::MAX, -i16::MAX, 0]
+            .into_iter()
+            .flat_map(i16::to_le_bytes)
+            .collect::<Vec<_>>();
+        let sample = parse_wav(&wav(1, 2, 44100, 16, &data)).unwrap();
+        assert_eq!((sample.rate, sample.channels), (44100, 2));
+        assert_eq!(sample.samples, [0.0, 1.0, -1.0, 0.0]);
+    }
+
+    #[test]
+    fn float_wav() {
+        let data = [0.5f32, -0.25]
+            .into_iter()
+            .flat_map(f32::to_le_bytes)
+            .collect::<Vec<_>>();
+        let sample = parse_wav(&wav(3, 1, 48000, 32, &data)).unwrap();
+        assert_eq!((sample.rate, sample.channels), (48000, 1));
+        assert_eq!(sample.samples, [0.5, -0.25]);
+    }
+
+    #[test]
+    fn broken_wav() {
+        let data = [1, 2, 3, 4, 5, 6];
+        let bytes = wav(1, 1, 8000, 16, &data);
+        // the headers and the chunks before the data
+        let data_start = bytes.len() - data.len();
+        for len in 0..data_start {
+            assert!(parse_wav(&bytes[..len]).is_none(), "cut at {len}");
+        }
+        // cut in the middle of the data, only the whole samples are kept
+        assert_eq!(
+            parse_wav(&bytes[..data_start + 3]).unwrap().samples.len(),
+            1
+        );
+
+        // 8 bit, 24 bit and ADPCM
+        assert!(parse_wav(&wav(1, 1, 8000, 8, &data)).is_none());
+        assert!(parse_wav(&wav(1, 1, 8000, 24, &data)).is_none());
+        assert!(parse_wav(&wav(2, 1, 8000, 4, &data)).is_none());
+        assert!(parse_wav(b"RIFX\0\0\0\0WAVE").is_none());
+        assert!(parse_wav(SILENCE_OGA).is_none());
+    }
+
+    #[test]
+    fn vorbis() {
+        let sample = decode_vorbis(SILENCE_OGA).unwrap();
+        assert_eq!((sample.rate, sample.channels), (8000, 1));
+        assert_eq!(sample.samples.len(), 384);
+        assert!(sample.samples.iter().all(|x| *x == 0.0));
+
+        // cut in the headers
+        assert!(decode_vorbis(&SILENCE_OGA[..100]).is_none());
+        // cut in the last page
+        assert!(decode_vorbis(&SILENCE_OGA[..SILENCE_OGA.len() - 10]).is_none());
+        assert!(decode_vorbis(&wav(1, 1, 8000, 16, &[0, 0])).is_none());
+    }
+
+    #[test]
+    fn themes_follow_inherits() {
+        let home = tempfile::tempdir().unwrap();
+        let system = tempfile::tempdir().unwrap();
+        let write = |dir: &Path, theme: &str, text: &str| {
+            let dir = theme_dir(dir, theme);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("index.theme"), text).unwrap();
+        };
+        // the one in the first data dir wins
+        write(
+            home.path(),
+            "custom",
+            "[Sound Theme]\nName=Custom\nInherits=ocean, yaru\n",
+        );
+        write(system.path(), "custom", "[Sound Theme]\nInherits=ignored\n");
+        write(
+            system.path(),
+            "ocean",
+            "[Other]\nInherits=ignored\n\n[Sound Theme]\nInherits=yaru\n",
+        );
+        write(system.path(), "yaru", "[Sound Theme]\nInherits=custom\n");
+
+        let dirs = [home.path().to_owned(), system.path().to_owned()];
+        assert_eq!(
+            themes(&dirs, "custom"),
+            ["custom", "ocean", "yaru", FALLBACK_THEME]
+        );
+        assert_eq!(themes(&dirs, FALLBACK_THEME), [FALLBACK_THEME]);
+    }
+}
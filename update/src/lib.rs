@@ -1,3 +1,8 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    hash::{BuildHasher, Hash},
+};
+
 pub mod macros {
     pub use update_derive::Update;
 }
@@ -6,3 +11,63 @@ pub trait Update {
     fn update(&mut self, other: Self);
     fn remove<T: AsRef<str>>(&mut self, properties_name: &[T]);
 }
+
+/// Merge used by fields marked `#[update(union)]`.
+///
+/// Sequences and sets only take the items they don't have yet, maps take every entry of `other`
+/// and overwrite the values of keys that already exist.
+pub trait Union {
+    fn union(&mut self, other: Self);
+}
+
+impl<T: PartialEq> Union for Vec<T> {
+    fn union(&mut self, other: Self) {
+        for item in other {
+            if !self.contains(&item) {
+                self.push(item);
+            }
+        }
+    }
+}
+
+impl<T: Eq + Hash, S: BuildHasher> Union for HashSet<T, S> {
+    fn union(&mut self, other: Self) {
+        self.extend(other);
+    }
+}
+
+impl<T: Ord> Union for BTreeSet<T> {
+    fn union(&mut self, other: Self) {
+        self.extend(other);
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> Union for HashMap<K, V, S> {
+    fn union(&mut self, other: Self) {
+        self.extend(other);
+    }
+}
+
+impl<K: Ord, V> Union for BTreeMap<K, V> {
+    fn union(&mut self, other: Self) {
+        self.extend(other);
+    }
+}
+
+/// Removal of a single entry, used by fields marked `#[update(remove_key)]`.
+pub trait RemoveKey {
+    /// Returns `true` if an entry with that key existed.
+    fn remove_key(&mut self, key: &str) -> bool;
+}
+
+impl<V, S: BuildHasher> RemoveKey for HashMap<String, V, S> {
+    fn remove_key(&mut self, key: &str) -> bool {
+        self.remove(key).is_some()
+    }
+}
+
+impl<V> RemoveKey for BTreeMap<String, V> {
+    fn remove_key(&mut self, key: &str) -> bool {
+        self.remove(key).is_some()
+    }
+}
@@ -0,0 +1,49 @@
+use update::macros::Update;
+use zvariant::Type;
+#[zvariant(signature = "a{sv}", rename_all = "PascalCase")]
+struct Properties {
+    playback_status: Option<String>,
+    can_go_next: Option<bool>,
+    #[zvariant(rename = "mpris:trackid")]
+    trackid: Option<String>,
+}
+impl ::zvariant::Type for Properties {
+    const SIGNATURE: &'static ::zvariant::Signature = &::zvariant::Signature::Dict {
+        key: ::zvariant::signature::Child::Static {
+            child: &::zvariant::Signature::Str,
+        },
+        value: ::zvariant::signature::Child::Static {
+            child: &::zvariant::Signature::Variant,
+        },
+    };
+}
+impl update::Update for Properties {
+    fn update(&mut self, other: Self) {
+        if let Some(other) = other.playback_status {
+            let _ = self.playback_status.replace(other);
+        }
+        if let Some(other) = other.can_go_next {
+            let _ = self.can_go_next.replace(other);
+        }
+        if let Some(other) = other.trackid {
+            let _ = self.trackid.replace(other);
+        }
+    }
+    fn remove<T: AsRef<str>>(&mut self, properties_name: &[T]) {
+        for name in properties_name {
+            match name.as_ref() {
+                "PlaybackStatus" => {
+                    let _ = self.playback_status.take();
+                }
+                "CanGoNext" => {
+                    let _ = self.can_go_next.take();
+                }
+                "mpris:trackid" => {
+                    let _ = self.trackid.take();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+fn main() {}
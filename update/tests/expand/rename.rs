@@ -0,0 +1,13 @@
+use update::macros::Update;
+use zvariant::Type;
+
+#[derive(Type, Update)]
+#[zvariant(signature = "a{sv}", rename_all = "PascalCase")]
+struct Properties {
+    playback_status: Option<String>,
+    can_go_next: Option<bool>,
+    #[zvariant(rename = "mpris:trackid")]
+    trackid: Option<String>,
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+use update::macros::Update;
+struct Properties {
+    title: Option<String>,
+    length: Option<i64>,
+}
+impl update::Update for Properties {
+    fn update(&mut self, other: Self) {
+        if let Some(other) = other.title {
+            let _ = self.title.replace(other);
+        }
+        if let Some(other) = other.length {
+            let _ = self.length.replace(other);
+        }
+    }
+    fn remove<T: AsRef<str>>(&mut self, properties_name: &[T]) {
+        for name in properties_name {
+            match name.as_ref() {
+                "title" => {
+                    let _ = self.title.take();
+                }
+                "length" => {
+                    let _ = self.length.take();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+fn main() {}
@@ -0,0 +1,9 @@
+use update::macros::Update;
+
+#[derive(Update)]
+struct Properties {
+    title: Option<String>,
+    length: Option<i64>,
+}
+
+fn main() {}
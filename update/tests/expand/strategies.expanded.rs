@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use update::macros::Update;
+struct Properties {
+    #[update(extend)]
+    artist: Option<Vec<String>>,
+    #[update(union)]
+    genre: Option<Vec<String>>,
+    #[update(union, remove_key)]
+    extra: Option<HashMap<String, u32>>,
+}
+impl update::Update for Properties {
+    fn update(&mut self, other: Self) {
+        if let Some(other) = other.artist {
+            match &mut self.artist {
+                Some(current) => {
+                    ::core::iter::Extend::extend(current, other);
+                }
+                None => {
+                    let _ = self.artist.replace(other);
+                }
+            }
+        }
+        if let Some(other) = other.genre {
+            update::Union::union(
+                self.genre.get_or_insert_with(::core::default::Default::default),
+                other,
+            );
+        }
+        if let Some(other) = other.extra {
+            update::Union::union(
+                self.extra.get_or_insert_with(::core::default::Default::default),
+                other,
+            );
+        }
+    }
+    fn remove<T: AsRef<str>>(&mut self, properties_name: &[T]) {
+        for name in properties_name {
+            match name.as_ref() {
+                "artist" => {
+                    let _ = self.artist.take();
+                }
+                "genre" => {
+                    let _ = self.genre.take();
+                }
+                "extra" => {
+                    let _ = self.extra.take();
+                }
+                name => {
+                    if let Some(current) = &mut self.extra {
+                        update::RemoveKey::remove_key(current, name);
+                    }
+                }
+            }
+        }
+    }
+}
+fn main() {}
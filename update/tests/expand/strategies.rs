@@ -0,0 +1,14 @@
+use std::collections::HashMap;
+use update::macros::Update;
+
+#[derive(Update)]
+struct Properties {
+    #[update(extend)]
+    artist: Option<Vec<String>>,
+    #[update(union)]
+    genre: Option<Vec<String>>,
+    #[update(union, remove_key)]
+    extra: Option<HashMap<String, u32>>,
+}
+
+fn main() {}
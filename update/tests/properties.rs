@@ -0,0 +1,169 @@
+use proptest::{collection, option, prelude::*};
+use std::collections::HashMap;
+use update::{Update, macros::Update};
+use zvariant::{DeserializeDict, OwnedValue, SerializeDict, Type};
+
+#[derive(SerializeDict, DeserializeDict, Type, Clone, Debug, Default, PartialEq, Update)]
+#[zvariant(signature = "a{sv}", rename_all = "PascalCase")]
+struct Properties {
+    playback_status: Option<String>,
+    volume: Option<f64>,
+    can_go_next: Option<bool>,
+    #[zvariant(rename = "xesam:artist")]
+    #[update(union)]
+    artist: Option<Vec<String>>,
+    #[update(union, remove_key)]
+    extra: Option<HashMap<String, u32>>,
+}
+
+const NAMES: [&str; 5] = [
+    "PlaybackStatus",
+    "Volume",
+    "CanGoNext",
+    "xesam:artist",
+    "Extra",
+];
+
+fn properties() -> impl Strategy<Value = Properties> {
+    (
+        option::of("[A-Z][a-z]{0,8}"),
+        option::of(-1e6f64..1e6),
+        option::of(any::<bool>()),
+        // `union` drops duplicates, `remove_then_update_restores` needs distinct artists
+        option::of(collection::hash_set("[a-z]{1,4}", 0..4).prop_map(Vec::from_iter)),
+        option::of(collection::hash_map("[a-z]{1,4}", any::<u32>(), 0..4)),
+    )
+        .prop_map(
+            |(playback_status, volume, can_go_next, artist, extra)| Properties {
+                playback_status,
+                volume,
+                can_go_next,
+                artist,
+                extra,
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn update_is_idempotent(mut current in properties(), other in properties()) {
+        current.update(other.clone());
+        let once = current.clone();
+        current.update(other);
+        prop_assert_eq!(current, once);
+    }
+
+    #[test]
+    fn update_with_default_changes_nothing(current in properties()) {
+        let mut updated = current.clone();
+        updated.update(Properties::default());
+        prop_assert_eq!(updated, current);
+    }
+
+    #[test]
+    fn remove_then_update_restores(current in properties()) {
+        let mut removed = current.clone();
+        removed.remove(&NAMES);
+        prop_assert_eq!(&removed, &Properties::default());
+        removed.update(current.clone());
+        prop_assert_eq!(removed, current);
+    }
+
+    #[test]
+    fn remove_one_property(current in properties(), index in 0..NAMES.len()) {
+        let mut removed = current.clone();
+        removed.remove(&[NAMES[index]]);
+        let mut expected = current;
+        match index {
+            0 => expected.playback_status = None,
+            1 => expected.volume = None,
+            2 => expected.can_go_next = None,
+            3 => expected.artist = None,
+            _ => expected.extra = None,
+        }
+        prop_assert_eq!(removed, expected);
+    }
+
+    #[test]
+    fn remove_map_entry(current in properties(), key in "[a-z]{1,4}") {
+        let mut removed = current.clone();
+        removed.remove(&[&key]);
+        let mut expected = current;
+        if let Some(extra) = &mut expected.extra {
+            extra.remove(&key);
+        }
+        prop_assert_eq!(removed, expected);
+    }
+
+    #[test]
+    fn union_keeps_both_sides(current in properties(), other in properties()) {
+        let mut updated = current.clone();
+        updated.update(other.clone());
+        let artist = updated.artist.unwrap_or_default();
+        for x in current.artist.iter().chain(other.artist.iter()).flatten() {
+            prop_assert!(artist.contains(x));
+        }
+        let extra = updated.extra.unwrap_or_default();
+        for key in current.extra.iter().chain(other.extra.iter()).flat_map(|x| x.keys()) {
+            prop_assert!(extra.contains_key(key));
+        }
+        for (key, value) in other.extra.iter().flatten() {
+            prop_assert_eq!(extra.get(key), Some(value));
+        }
+    }
+
+    #[test]
+    fn union_drops_duplicates(
+        current in option::of(collection::hash_set("[a-c]", 0..3).prop_map(Vec::from_iter)),
+        other in collection::vec("[a-c]", 0..6),
+    ) {
+        let mut updated = Properties { artist: current, ..Default::default() };
+        updated.update(Properties { artist: Some(other), ..Default::default() });
+        let artist = updated.artist.unwrap();
+        for (i, x) in artist.iter().enumerate() {
+            prop_assert!(!artist[i + 1..].contains(x), "{:?}", artist);
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Update)]
+struct Collections {
+    #[update(extend)]
+    items: Option<Vec<u8>>,
+    #[update(extend, remove_key)]
+    values: Option<HashMap<String, OwnedValue>>,
+}
+
+proptest! {
+    #[test]
+    fn extend_appends(
+        current in option::of(collection::vec(any::<u8>(), 0..8)),
+        other in option::of(collection::vec(any::<u8>(), 0..8)),
+    ) {
+        let mut updated = Collections { items: current.clone(), values: None };
+        updated.update(Collections { items: other.clone(), values: None });
+        let expected = match (current, other) {
+            (Some(mut current), Some(other)) => {
+                current.extend(other);
+                Some(current)
+            }
+            (current, other) => other.or(current),
+        };
+        prop_assert_eq!(updated.items, expected);
+    }
+}
+
+#[test]
+fn remove_key_of_value_map() {
+    let mut current = Collections {
+        items: None,
+        values: Some(HashMap::from([
+            ("a".to_owned(), OwnedValue::from(1u32)),
+            ("b".to_owned(), OwnedValue::from(2u32)),
+        ])),
+    };
+    current.remove(&["a", "c"]);
+    let values = current.values.unwrap();
+    assert_eq!(values.len(), 1);
+    assert!(values.contains_key("b"));
+}
@@ -1,35 +1,203 @@
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::Data;
+use syn::{Attribute, Data, Field, LitStr, Token};
 
-#[proc_macro_derive(Update)]
+/// Every field must be an `Option`, `None` means "not present".
+///
+/// `remove` matches the names the fields are serialized with, so `#[zvariant(rename = "...")]` on
+/// a field and `#[zvariant(rename_all = "...")]` on the struct are honored. They are only read, a
+/// zvariant derive such as `Type` must be next to this one to accept them.
+///
+/// Field attributes:
+/// - `#[update(replace)]` (default): `update` replaces the whole value.
+/// - `#[update(extend)]`: `update` appends the new items with [`Extend`].
+/// - `#[update(union)]`: `update` merges the new items with `update::Union`, into an empty
+///   collection if there is none, so the field must implement [`Default`].
+/// - `#[update(remove_key)]`: names passed to `remove` that aren't a field name remove the entry
+///   with that key from this field (see `update::RemoveKey`).
+#[proc_macro_derive(Update, attributes(update))]
 pub fn update_macro_derive(item: TokenStream) -> TokenStream {
     let ast = syn::parse(item).unwrap();
     impl_update_macro(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
 
-fn impl_update_macro(ast: &syn::DeriveInput) -> TokenStream {
+#[derive(Default)]
+enum Strategy {
+    #[default]
+    Replace,
+    Extend,
+    Union,
+}
+
+#[derive(Default)]
+struct FieldAttributes {
+    strategy: Strategy,
+    remove_key: bool,
+    rename: Option<String>,
+}
+
+impl FieldAttributes {
+    fn parse(field: &Field) -> syn::Result<Self> {
+        let mut attributes = Self {
+            rename: zvariant_attribute(&field.attrs, "rename")?,
+            ..Default::default()
+        };
+        for attr in field.attrs.iter().filter(|x| x.path().is_ident("update")) {
+            attr.parse_nested_meta(|meta| {
+                let strategy = if meta.path.is_ident("replace") {
+                    Strategy::Replace
+                } else if meta.path.is_ident("extend") {
+                    Strategy::Extend
+                } else if meta.path.is_ident("union") {
+                    Strategy::Union
+                } else if meta.path.is_ident("remove_key") {
+                    attributes.remove_key = true;
+                    return Ok(());
+                } else {
+                    return Err(meta.error("expected `replace`, `extend`, `union` or `remove_key`"));
+                };
+                attributes.strategy = strategy;
+                Ok(())
+            })?;
+        }
+        Ok(attributes)
+    }
+}
+
+/// Finds `key = "value"` in the `#[zvariant(...)]` attributes, ignoring the other keys.
+fn zvariant_attribute(attrs: &[Attribute], key: &str) -> syn::Result<Option<String>> {
+    let mut value = None;
+    for attr in attrs.iter().filter(|x| x.path().is_ident("zvariant")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) {
+                value = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else if meta.input.peek(Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(value)
+}
+
+/// Same rules as serde's `rename_all`, `name` is expected to be in snake_case.
+fn rename_all(name: &str, rule: &str) -> syn::Result<String> {
+    let pascal_case = || {
+        name.split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|x| x.to_ascii_uppercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            })
+            .collect::<String>()
+    };
+    Ok(match rule {
+        "lowercase" | "snake_case" => name.to_owned(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => name.to_ascii_uppercase(),
+        "PascalCase" => pascal_case(),
+        "camelCase" => {
+            let pascal_case = pascal_case();
+            let mut chars = pascal_case.chars();
+            chars
+                .next()
+                .map(|x| x.to_ascii_lowercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        }
+        "kebab-case" => name.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => name.to_ascii_uppercase().replace('_', "-"),
+        _ => {
+            return Err(syn::Error::new(
+                proc_macro2::Span::call_site(),
+                format!("unknown `rename_all` rule: {rule:?}"),
+            ));
+        }
+    })
+}
+
+fn impl_update_macro(ast: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &ast.ident;
-    assert!(matches!(ast.data, Data::Struct(_)));
+    let rename_rule = zvariant_attribute(&ast.attrs, "rename_all")?;
     let Data::Struct(s) = &ast.data else {
-        unreachable!()
+        return Err(syn::Error::new_spanned(
+            ast,
+            "`Update` can only be derived for structs",
+        ));
     };
-    let update_fields = s.fields.iter().map(|field| {
+    let fields = s
+        .fields
+        .iter()
+        .map(|field| Ok((field, FieldAttributes::parse(field)?)))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let update_fields = fields.iter().map(|(field, attributes)| {
         let name = field.ident.as_ref().unwrap();
+        let merge = match attributes.strategy {
+            Strategy::Replace => {
+                return quote! {
+                    if let Some(other) = other.#name {
+                        let _ = self.#name.replace(other);
+                    }
+                };
+            }
+            Strategy::Extend => quote! { ::core::iter::Extend::extend(current, other); },
+            // `None` is merged like an empty collection, so its duplicates are dropped too
+            Strategy::Union => {
+                return quote! {
+                    if let Some(other) = other.#name {
+                        update::Union::union(
+                            self.#name.get_or_insert_with(::core::default::Default::default),
+                            other,
+                        );
+                    }
+                };
+            }
+        };
         quote! {
             if let Some(other) = other.#name {
-                let _ = self.#name.replace(other);
+                match &mut self.#name {
+                    Some(current) => { #merge }
+                    None => { let _ = self.#name.replace(other); }
+                }
             }
         }
     });
-    let remove_fields = s.fields.iter().map(|field| {
-        let name = field.ident.as_ref().unwrap();
-        let name_string = name.to_string();
-        quote! {
-            #name_string => { let _ = self.#name.take(); }
-        }
-    });
-    quote! {
+    let remove_fields = fields
+        .iter()
+        .map(|(field, attributes)| {
+            let name = field.ident.as_ref().unwrap();
+            let name_string = match (&attributes.rename, &rename_rule) {
+                (Some(rename), _) => rename.clone(),
+                (None, Some(rule)) => rename_all(&name.to_string(), rule)?,
+                (None, None) => name.to_string(),
+            };
+            Ok(quote! {
+                #name_string => { let _ = self.#name.take(); }
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    let remove_keys = fields
+        .iter()
+        .filter(|(_, attributes)| attributes.remove_key)
+        .map(|(field, _)| {
+            let name = field.ident.as_ref().unwrap();
+            quote! {
+                if let Some(current) = &mut self.#name {
+                    update::RemoveKey::remove_key(current, name);
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+    let remove_other = if remove_keys.is_empty() {
+        quote! { _ => () }
+    } else {
+        quote! { name => { #( #remove_keys )* } }
+    };
+
+    Ok(quote! {
         impl update::Update for #name {
             fn update(&mut self, other: Self) {
                 #( #update_fields )*
@@ -38,11 +206,10 @@ fn impl_update_macro(ast: &syn::DeriveInput) -> TokenStream {
                 for name in properties_name {
                     match name.as_ref() {
                         #( #remove_fields )*
-                        _ => (),
+                        #remove_other
                     }
                 }
             }
         }
-    }
-    .into()
+    })
 }